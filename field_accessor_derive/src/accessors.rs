@@ -0,0 +1,227 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::model::{FieldInfo, Shape};
+
+/// Generates the `match self` expressions shared by the accessor methods.
+pub(crate) struct Accessors<'s, 'a> {
+    pub ident: &'s syn::Ident,
+    pub vis: &'s syn::Visibility,
+    pub shapes: &'s [Shape<'a>],
+    pub keys: &'s [&'s FieldInfo<'a>],
+    pub invariant: Option<&'s syn::Path>,
+}
+
+impl Accessors<'_, '_> {
+    /// Matches `field_string` against the fields of the active shape and evaluates `body`
+    /// with the field bound. Only fields of type `ty` are accepted if it is given; the
+    /// others are reported as a type mismatch. If `writes` is set, read-only fields are
+    /// rejected.
+    pub fn match_field(
+        &self,
+        ty: Option<&syn::Type>,
+        writes: bool,
+        body: impl Fn(&FieldInfo) -> TokenStream,
+    ) -> TokenStream {
+        let rejects = |f: &FieldInfo| writes && f.readonly;
+        let accepts = |f: &FieldInfo| {
+            !rejects(f)
+                && match ty {
+                    Some(ty) => f.ty == ty,
+                    None => true,
+                }
+        };
+        let arms = self.shapes.iter().map(|shape| {
+            let pattern = shape.pattern(accepts);
+            let (names, bodies): (Vec<_>, Vec<_>) = shape
+                .fields
+                .iter()
+                .filter(|f| accepts(f))
+                .map(|f| (&f.name, body(f)))
+                .unzip();
+            let readonly_names = shape.fields.iter().filter(|f| rejects(f)).map(|f| &f.name);
+            let (mismatch_names, mismatch_tys): (Vec<_>, Vec<_>) = shape
+                .fields
+                .iter()
+                .filter(|f| !accepts(f) && !rejects(f))
+                .map(|f| (&f.name, f.ty))
+                .unzip();
+            let missing = self.missing_field(shape, &quote!(field_string));
+            quote! {
+                #pattern => match field_string {
+                    #(#names => #bodies,)*
+                    #(#readonly_names => Err(::field_accessor::FieldAccessError::ReadOnlyField {
+                        field: field_string.to_string(),
+                    }),)*
+                    #(#mismatch_names => Err(::field_accessor::FieldAccessError::TypeMismatch {
+                        field: field_string.to_string(),
+                        expected: stringify!(#ty),
+                        found: stringify!(#mismatch_tys),
+                    }),)*
+                    _ => Err(#missing),
+                }
+            }
+        });
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        }
+    }
+
+    /// Swaps two fields of the same type within the active shape. If `validates` is set,
+    /// each value is checked with the validators of the field it moves to first.
+    pub fn swap(&self, validates: bool) -> TokenStream {
+        let arms = self.shapes.iter().map(|shape| {
+            let mut pairs = vec![];
+            for outer in shape.fields.iter() {
+                for inner in shape.fields.iter() {
+                    if outer.ty == inner.ty
+                        && outer.member != inner.member
+                        && !outer.readonly
+                        && !inner.readonly
+                    {
+                        pairs.push((outer, inner));
+                    }
+                }
+            }
+            let pattern = shape.pattern(|f| pairs.iter().any(|(outer, _)| outer.name == f.name));
+            let swap_tys = pairs.iter().map(|(outer, _)| outer.ty);
+            let swap_name = pairs.iter().map(|(outer, _)| &outer.name);
+            let swap_name2 = pairs.iter().map(|(_, inner)| &inner.name);
+            let swap_binding = pairs.iter().map(|(outer, _)| &outer.binding);
+            let swap_binding2 = pairs.iter().map(|(_, inner)| &inner.binding);
+            let validates = pairs.iter().map(|(outer, inner)| {
+                if !validates {
+                    return quote!();
+                }
+                let (binding, binding2) = (&outer.binding, &inner.binding);
+                let validate = self.validate(outer, quote!(&*#binding2));
+                let validate2 = self.validate(inner, quote!(&*#binding));
+                quote!(#validate #validate2)
+            });
+            let shape_names = shape.names();
+            let readonly_names = shape.fields.iter().filter(|f| f.readonly).map(|f| &f.name);
+            let missing = self.missing_field(shape, &quote!(field_string));
+            quote! {
+                #pattern => match (field_string, field_string_y) {
+                    #(
+                        (#swap_name, #swap_name2) => {
+                            #validates
+                            std::mem::swap::<#swap_tys>(#swap_binding, #swap_binding2);
+                            Ok(())
+                        }
+                    )*
+                    _ => {
+                        for field_string in [field_string, field_string_y] {
+                            if ![#(#shape_names),*].contains(&field_string) {
+                                return Err(#missing);
+                            }
+                        }
+                        for field_string in [field_string, field_string_y] {
+                            if [#(#readonly_names),*].contains(&field_string) {
+                                return Err(::field_accessor::FieldAccessError::ReadOnlyField {
+                                    field: field_string.to_string(),
+                                });
+                            }
+                        }
+                        Err(::field_accessor::FieldAccessError::IncompatibleSwap {
+                            field: field_string.to_string(),
+                            field_y: field_string_y.to_string(),
+                        })
+                    }
+                }
+            }
+        });
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        }
+    }
+
+    /// Checks `value`, a reference to a new value for `f`, with the field's validators and
+    /// returns early if it fails. Fields of enum variants that share a name use the
+    /// validators of the first one.
+    pub fn validate(&self, f: &FieldInfo, value: TokenStream) -> TokenStream {
+        let key = self.keys.iter().find(|key| key.name == f.name).unwrap();
+        if key.validators.is_empty() {
+            return quote!();
+        }
+        let refname = format_ident!("{}FieldRef", self.ident);
+        let variant = &f.variant;
+        quote!(Self::__validate(#refname::#variant(#value))?;)
+    }
+
+    /// Validates `value` and assigns it to `f`. The expression evaluates to `Ok(())`, or with
+    /// an invariant to the field's previous value as `(StructName)FieldEnum`, which
+    /// [`Self::guard`] restores if the invariant fails.
+    pub fn assign(&self, f: &FieldInfo, value: TokenStream) -> TokenStream {
+        let binding = &f.binding;
+        let validate = self.validate(f, quote!(&#value));
+        if self.invariant.is_none() {
+            return quote!({ #validate *#binding = #value; Ok(()) });
+        }
+        let enumname = format_ident!("{}FieldEnum", self.ident);
+        let variant = &f.variant;
+        quote!({ #validate Ok(#enumname::#variant(std::mem::replace(#binding, #value))) })
+    }
+
+    /// Evaluates `assign`, an expression built from [`Self::assign`], and checks the
+    /// invariant afterwards.
+    pub fn guard(&self, assign: TokenStream) -> TokenStream {
+        if self.invariant.is_none() {
+            return assign;
+        }
+        let check = self.check_invariant(quote!(self.__replace_field(previous)?;));
+        quote! {
+            let previous = #assign?;
+            #check
+            Ok(())
+        }
+    }
+
+    /// Runs the invariant and, if it fails, `restore` before returning the error.
+    pub fn check_invariant(&self, restore: TokenStream) -> TokenStream {
+        if self.invariant.is_none() {
+            return quote!();
+        }
+        quote! {
+            if let Err(error) = self.__check_invariant() {
+                #restore
+                return Err(error);
+            }
+        }
+    }
+
+    /// The error for a field name that the given shape does not have.
+    pub fn missing_field(&self, shape: &Shape, field_string: &TokenStream) -> TokenStream {
+        let ident = self.ident;
+        let unknown_field = quote! {
+            ::field_accessor::FieldAccessError::UnknownField {
+                struct_name: stringify!(#ident),
+                field: #field_string.to_string(),
+            }
+        };
+        let variant = match shape.variant {
+            Some(variant) => variant.to_string(),
+            None => return unknown_field,
+        };
+        let absent: Vec<_> = self
+            .keys
+            .iter()
+            .filter(|key| !shape.fields.iter().any(|f| f.name == key.name))
+            .map(|key| &key.name)
+            .collect();
+        quote! {
+            if [#(#absent),*].contains(&#field_string) {
+                ::field_accessor::FieldAccessError::FieldNotInVariant {
+                    variant: #variant,
+                    field: #field_string.to_string(),
+                }
+            } else {
+                #unknown_field
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use syn::{Lit, Meta, NestedMeta};
+
+const ATTR: &str = "field_accessor";
+
+/// Options set on a field with `#[field_accessor(...)]`.
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// The field is not exposed at all.
+    pub skip: bool,
+    /// The name the field is looked up by instead of its own.
+    pub rename: Option<String>,
+    /// The field can be read but not modified.
+    pub readonly: bool,
+    /// Paths may continue into the field, whose type also derives `FieldAccessor`.
+    pub nested: bool,
+    /// The field's type has no `FromStr` impl, so `set_from_str` rejects it.
+    pub no_parse: bool,
+    /// The field is formatted with `Debug` instead of `Display`.
+    pub debug_fmt: bool,
+    /// Checks a new value must pass before it is assigned to the field.
+    pub validators: Vec<Validator>,
+}
+
+/// A check set with `validate`, `range`, `min_len`, `max_len` or `regex`.
+pub(crate) enum Validator {
+    /// A function taking the new value by reference and returning
+    /// `Result<(), impl Display>`.
+    Custom(syn::Path),
+    Range {
+        min: Option<Box<Bound>>,
+        max: Option<Box<Bound>>,
+    },
+    MinLen(usize),
+    MaxLen(usize),
+    Regex(syn::LitStr),
+}
+
+/// A bound of `range`, with the text it is reported as.
+pub(crate) struct Bound {
+    pub expr: syn::Expr,
+    pub text: String,
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut parsed = FieldAttrs::default();
+        for meta in nested_metas(attrs)? {
+            match &meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    set_flag(&mut parsed.skip, &meta)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("readonly") => {
+                    set_flag(&mut parsed.readonly, &meta)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nested") => {
+                    set_flag(&mut parsed.nested, &meta)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("no_parse") => {
+                    set_flag(&mut parsed.no_parse, &meta)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("debug_fmt") => {
+                    set_flag(&mut parsed.debug_fmt, &meta)?;
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("validate") => {
+                    match &nv.lit {
+                        Lit::Str(s) => parsed.validators.push(Validator::Custom(s.parse()?)),
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected a function path, e.g. `validate = \"check_age\"`",
+                            ))
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("range") => {
+                    parsed.validators.push(parse_range(list)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("min_len") => {
+                    parsed.validators.push(Validator::MinLen(parse_len(&nv.lit)?));
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max_len") => {
+                    parsed.validators.push(Validator::MaxLen(parse_len(&nv.lit)?));
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("regex") => {
+                    parsed.validators.push(Validator::Regex(parse_regex(&nv.lit)?));
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    if parsed.rename.is_some() {
+                        return Err(syn::Error::new_spanned(meta, "duplicate `rename` option"));
+                    }
+                    match &nv.lit {
+                        Lit::Str(s) if !s.value().is_empty() => parsed.rename = Some(s.value()),
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected a non-empty string, e.g. `rename = \"newName\"`",
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "unknown field_accessor option; expected `skip`, `rename = \"...\"`, `readonly`, `nested`, `no_parse`, `debug_fmt`, `validate = \"...\"`, `range(...)`, `min_len = ...`, `max_len = ...` or `regex = \"...\"`",
+                    ))
+                }
+            }
+        }
+        if parsed.skip
+            && (parsed.rename.is_some()
+                || parsed.readonly
+                || parsed.nested
+                || parsed.no_parse
+                || parsed.debug_fmt
+                || !parsed.validators.is_empty())
+        {
+            return Err(syn::Error::new_spanned(
+                attrs.iter().find(|attr| attr.path.is_ident(ATTR)),
+                "`skip` cannot be combined with other field_accessor options",
+            ));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Parses `range(min = ..., max = ...)`, where either bound may be left out. A bound is
+/// a number, or a string holding an expression such as `"-5"` or `"i32::MAX"`.
+fn parse_range(list: &syn::MetaList) -> syn::Result<Validator> {
+    let (mut min, mut max) = (None, None);
+    for nested in list.nested.iter() {
+        let (bound, nv) = match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("min") => (&mut min, nv),
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max") => (&mut max, nv),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "expected `min = ...` or `max = ...`",
+                ))
+            }
+        };
+        if bound.is_some() {
+            return Err(syn::Error::new_spanned(nested, "duplicate range bound"));
+        }
+        *bound = Some(Box::new(match &nv.lit {
+            Lit::Int(int) => Bound {
+                expr: syn::parse_quote!(#int),
+                text: int.to_string(),
+            },
+            Lit::Float(float) => Bound {
+                expr: syn::parse_quote!(#float),
+                text: float.to_string(),
+            },
+            Lit::Str(s) => Bound {
+                expr: s.parse()?,
+                text: s.value(),
+            },
+            lit => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "expected a number, or an expression in a string, e.g. `min = \"-5\"`",
+                ))
+            }
+        }));
+    }
+    if min.is_none() && max.is_none() {
+        return Err(syn::Error::new_spanned(
+            list,
+            "expected a bound, e.g. `range(min = 0, max = 150)`",
+        ));
+    }
+    Ok(Validator::Range { min, max })
+}
+
+fn parse_len(lit: &Lit) -> syn::Result<usize> {
+    match lit {
+        Lit::Int(int) => int.base10_parse(),
+        lit => Err(syn::Error::new_spanned(
+            lit,
+            "expected a length, e.g. `min_len = 1`",
+        )),
+    }
+}
+
+/// The pattern of `regex = "..."`, which is checked here so that a typo fails the build.
+fn parse_regex(lit: &Lit) -> syn::Result<syn::LitStr> {
+    let pattern = match lit {
+        Lit::Str(s) => s,
+        lit => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "expected a pattern, e.g. `regex = \"^[a-z]+$\"`",
+            ))
+        }
+    };
+    check_regex(pattern)?;
+    Ok(pattern.clone())
+}
+
+#[cfg(feature = "regex")]
+fn check_regex(pattern: &syn::LitStr) -> syn::Result<()> {
+    regex::Regex::new(&pattern.value())
+        .map(drop)
+        .map_err(|error| syn::Error::new_spanned(pattern, error))
+}
+
+#[cfg(not(feature = "regex"))]
+fn check_regex(pattern: &syn::LitStr) -> syn::Result<()> {
+    Err(syn::Error::new_spanned(
+        pattern,
+        "`regex` requires the `regex` feature of field_accessor",
+    ))
+}
+
+/// Options set on the derived type with `#[field_accessor(...)]`.
+#[derive(Default)]
+pub(crate) struct ContainerAttrs {
+    /// A `(StructName)Tracked` wrapper recording which fields were modified is generated.
+    pub track_changes: bool,
+    /// A `(StructName)Observed` wrapper calling listeners on every change is generated.
+    pub observe: bool,
+    /// A `(StructName)History` wrapper recording changes that can be undone is generated.
+    pub history: bool,
+    /// A function checking the whole value after each change, which is undone if it fails.
+    pub invariant: Option<syn::Path>,
+}
+
+impl ContainerAttrs {
+    pub fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut parsed = ContainerAttrs::default();
+        for meta in nested_metas(attrs)? {
+            match &meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("track_changes") => {
+                    set_flag(&mut parsed.track_changes, &meta)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("observe") => {
+                    set_flag(&mut parsed.observe, &meta)?;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("history") => {
+                    set_flag(&mut parsed.history, &meta)?;
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("invariant") => {
+                    if parsed.invariant.is_some() {
+                        return Err(syn::Error::new_spanned(meta, "duplicate `invariant` option"));
+                    }
+                    match &nv.lit {
+                        Lit::Str(s) => parsed.invariant = Some(s.parse()?),
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected a function path, e.g. `invariant = \"Self::check\"`",
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(syn::Error::new_spanned(
+                    meta,
+                    "unknown field_accessor option for the derived type; expected `track_changes`, `observe`, `history` or `invariant = \"...\"`",
+                )),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Fails if `#[field_accessor(...)]` is used somewhere it has no meaning.
+pub(crate) fn reject(attrs: &[syn::Attribute], position: &str) -> syn::Result<()> {
+    match attrs.iter().find(|attr| attr.path.is_ident(ATTR)) {
+        Some(attr) => Err(syn::Error::new_spanned(
+            attr,
+            format!("field_accessor options are not supported on {}", position),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn nested_metas(attrs: &[syn::Attribute]) -> syn::Result<Vec<NestedMeta>> {
+    let mut metas = vec![];
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident(ATTR)) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected a list of options, e.g. `#[field_accessor(skip)]`",
+                ))
+            }
+        }
+    }
+    Ok(metas)
+}
+
+fn set_flag(flag: &mut bool, meta: &NestedMeta) -> syn::Result<()> {
+    if *flag {
+        return Err(syn::Error::new_spanned(
+            meta,
+            "duplicate field_accessor option",
+        ));
+    }
+    *flag = true;
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::{field_bound, static_bounds, where_with};
+
+/// Generates `diff`, which compares two instances field by field, and `diff_nested`, which
+/// also walks into nested fields through `field_accessor::__private::FieldDiff`.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let ident = accessors.ident;
+    let refname = format_ident!("{}FieldRef", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut ref_generics = generics.clone();
+    ref_generics.params.insert(0, syn::parse_quote!('__fa));
+    let (_, ref_ty_generics, _) = ref_generics.split_for_impl();
+
+    let names: Vec<_> = accessors.keys.iter().map(|f| &f.name).collect();
+    let eq_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .map(|f| field_bound(f.ty, generics, &quote!(::core::cmp::PartialEq)))
+        .collect();
+    let diff_where = if eq_bounds.is_empty() {
+        quote!()
+    } else {
+        quote!(where #(#eq_bounds),*)
+    };
+
+    // Nested fields are compared by their own `FieldDiff` impl instead of `PartialEq`.
+    let nested_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .map(|f| {
+            let bound = if f.nested {
+                quote!(::field_accessor::__private::FieldDiff)
+            } else {
+                quote!(::core::cmp::PartialEq)
+            };
+            field_bound(f.ty, generics, &bound)
+        })
+        .collect();
+    let nested_where = where_with(generics, &[static_bounds(generics), nested_bounds].concat());
+    let compare = accessors.keys.iter().map(|f| {
+        let name = &f.name;
+        let variant = &f.variant;
+        let body = if f.nested {
+            quote! {
+                ::field_accessor::__private::FieldDiff::__diff_fields(
+                    old,
+                    new,
+                    &format!("{}{}.", prefix, #name),
+                    changes,
+                );
+            }
+        } else {
+            quote! {
+                if old != new {
+                    changes.push(::field_accessor::FieldChange {
+                        field: format!("{}{}", prefix, #name),
+                        old: Some(old as &dyn ::core::any::Any),
+                        new: Some(new as &dyn ::core::any::Any),
+                    });
+                }
+            }
+        };
+        // A field that only one side's variant has is reported whole.
+        quote! {
+            match (self.getenum_ref(#name), other.getenum_ref(#name)) {
+                (Ok(#refname::#variant(old)), Ok(#refname::#variant(new))) => {
+                    #body
+                }
+                (Ok(#refname::#variant(old)), _) => changes.push(::field_accessor::FieldChange {
+                    field: format!("{}{}", prefix, #name),
+                    old: Some(old as &dyn ::core::any::Any),
+                    new: None,
+                }),
+                (_, Ok(#refname::#variant(new))) => changes.push(::field_accessor::FieldChange {
+                    field: format!("{}{}", prefix, #name),
+                    old: None,
+                    new: Some(new as &dyn ::core::any::Any),
+                }),
+                _ => {}
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::field_accessor::__private::FieldDiff for #ident #ty_generics #nested_where {
+            #[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
+            fn __diff_fields<'__fa>(
+                &'__fa self,
+                other: &'__fa Self,
+                prefix: &str,
+                changes: &mut Vec<::field_accessor::FieldChange<&'__fa dyn ::core::any::Any>>,
+            ) {
+                #(#compare)*
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn diff<'__fa>(&'__fa self, other: &'__fa Self) -> Vec<::field_accessor::FieldChange<#refname #ref_ty_generics>> #diff_where {
+                let mut changes = vec![];
+                let fields: &[&str] = &[#(#names),*];
+                for field in fields {
+                    // Fields outside the active variant of both sides have not changed.
+                    let old = self.getenum_ref(field).ok();
+                    let new = other.getenum_ref(field).ok();
+                    if old != new {
+                        changes.push(::field_accessor::FieldChange {
+                            field: field.to_string(),
+                            old,
+                            new,
+                        });
+                    }
+                }
+                changes
+            }
+
+            fn diff_nested<'__fa>(&'__fa self, other: &'__fa Self) -> Vec<::field_accessor::FieldChange<&'__fa dyn ::core::any::Any>>
+            where
+                for<'__bound> Self: ::field_accessor::__private::FieldDiff,
+            {
+                let mut changes = vec![];
+                ::field_accessor::__private::FieldDiff::__diff_fields(self, other, "", &mut changes);
+                changes
+            }
+        }
+    }
+}
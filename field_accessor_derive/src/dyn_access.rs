@@ -0,0 +1,89 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::accessors::Accessors;
+use crate::generics::{static_bounds, where_with};
+
+/// Implements `field_accessor::DynFieldAccess`.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let ident = accessors.ident;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let where_clause = where_with(generics, &static_bounds(generics));
+
+    let get_dyn = accessors.match_field(None, false, |f| {
+        let binding = &f.binding;
+        quote!(Ok(#binding as &dyn ::core::any::Any))
+    });
+    let get_dyn_mut = accessors.match_field(None, true, |f| {
+        let binding = &f.binding;
+        quote!(Ok(#binding as &mut dyn ::core::any::Any))
+    });
+    // The given value is named like `set` names the requested type, if it has one of the
+    // field types.
+    let mut tys: Vec<&syn::Type> = vec![];
+    for key in accessors.keys.iter() {
+        if !tys.contains(&key.ty) {
+            tys.push(key.ty);
+        }
+    }
+    let given = if tys.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            let given = |value: &dyn ::core::any::Any| -> &'static str {
+                #(
+                    if value.is::<#tys>() {
+                        return stringify!(#tys);
+                    }
+                )*
+                "dyn Any"
+            };
+        }
+    };
+    let set_dyn = accessors.guard(accessors.match_field(None, true, |f| {
+        let ty = f.ty;
+        let assign = accessors.assign(f, quote!(*value));
+        quote! {
+            match value.downcast::<#ty>() {
+                Ok(value) => #assign
+                Err(value) => Err(::field_accessor::FieldAccessError::TypeMismatch {
+                    field: field_string.to_string(),
+                    expected: given(&*value),
+                    found: stringify!(#ty),
+                }),
+            }
+        }
+    }));
+    let field_names_arms = accessors.shapes.iter().map(|shape| {
+        let pattern = shape.pattern(|_| false);
+        let names = shape.names();
+        quote!(#pattern => &[#(#names),*],)
+    });
+
+    quote! {
+        impl #impl_generics ::field_accessor::DynFieldAccess for #ident #ty_generics #where_clause {
+            fn get_dyn(&self, field_string: &str) -> Result<&dyn ::core::any::Any, ::field_accessor::FieldAccessError> {
+                #get_dyn
+            }
+
+            fn get_dyn_mut(&mut self, field_string: &str) -> Result<&mut dyn ::core::any::Any, ::field_accessor::FieldAccessError> {
+                #get_dyn_mut
+            }
+
+            fn set_dyn(&mut self, field_string: &str, value: Box<dyn ::core::any::Any>) -> Result<(), ::field_accessor::FieldAccessError> {
+                #given
+                #set_dyn
+            }
+
+            fn field_names(&self) -> &'static [&'static str] {
+                match self {
+                    #(#field_names_arms)*
+                }
+            }
+
+            fn type_name(&self) -> &'static str {
+                stringify!(#ident)
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+
+fn param_ident(param: &syn::GenericParam) -> &syn::Ident {
+    match param {
+        syn::GenericParam::Type(t) => &t.ident,
+        syn::GenericParam::Lifetime(l) => &l.lifetime.ident,
+        syn::GenericParam::Const(c) => &c.ident,
+    }
+}
+
+fn mentions_ident(ty: &syn::Type, idents: &[&syn::Ident]) -> bool {
+    fn walk(tokens: TokenStream, idents: &[&syn::Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => idents.iter().any(|i| ident == **i),
+            TokenTree::Group(group) => walk(group.stream(), idents),
+            _ => false,
+        })
+    }
+    walk(quote!(#ty), idents)
+}
+
+/// Returns true if `ty` refers to one of the struct's type, const or lifetime parameters.
+pub(crate) fn mentions_generic_param(ty: &syn::Type, generics: &syn::Generics) -> bool {
+    let params: Vec<_> = generics.params.iter().map(param_ident).collect();
+    mentions_ident(ty, &params)
+}
+
+/// A hidden, uninhabited variant that uses the type and lifetime parameters none of
+/// `tys` mention, so that a generated enum holding `tys` still compiles.
+pub(crate) fn phantom_variant(generics: &syn::Generics, tys: &[&syn::Type]) -> TokenStream {
+    let unused = |ident: &syn::Ident| !tys.iter().any(|ty| mentions_ident(ty, &[ident]));
+    let lifetimes = generics
+        .lifetimes()
+        .map(|l| &l.lifetime)
+        .filter(|l| unused(&l.ident));
+    let types = generics
+        .type_params()
+        .map(|t| &t.ident)
+        .filter(|t| unused(t));
+    let phantom: Vec<_> = lifetimes
+        .map(|l| quote!(&#l ()))
+        .chain(types.map(|t| quote!(#t)))
+        .collect();
+    if phantom.is_empty() {
+        return quote!();
+    }
+    quote! {
+        #[doc(hidden)]
+        __Phantom(::core::marker::PhantomData<(#(#phantom,)*)>, ::core::convert::Infallible),
+    }
+}
+
+/// The struct's where-clause extended with `bounds`.
+pub(crate) fn where_with(generics: &syn::Generics, bounds: &[TokenStream]) -> TokenStream {
+    let predicates = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter());
+    if generics.where_clause.is_none() && bounds.is_empty() {
+        return quote!();
+    }
+    quote!(where #(#predicates,)* #(#bounds,)*)
+}
+
+/// The bound `ty: bound`. For types that do not depend on the struct's generics it is
+/// made higher-ranked, so that an impl or method carrying it is merely unavailable,
+/// rather than a compile error, when the type does not satisfy it.
+pub(crate) fn field_bound(
+    ty: &syn::Type,
+    generics: &syn::Generics,
+    bound: &TokenStream,
+) -> TokenStream {
+    if mentions_generic_param(ty, generics) {
+        quote!(#ty: #bound)
+    } else {
+        quote!(for<'__bound> #ty: #bound)
+    }
+}
+
+/// Bounds making every type and lifetime parameter `'static`, as `dyn Any` requires.
+pub(crate) fn static_bounds(generics: &syn::Generics) -> Vec<TokenStream> {
+    let lifetimes = generics.lifetimes().map(|l| {
+        let lifetime = &l.lifetime;
+        quote!(#lifetime: 'static)
+    });
+    let types = generics.type_params().map(|t| {
+        let ident = &t.ident;
+        quote!(#ident: 'static)
+    });
+    lifetimes.chain(types).collect()
+}
@@ -0,0 +1,189 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::{field_bound, where_with};
+
+/// Generates `(StructName)History` for `#[field_accessor(history)]`, a wrapper that records
+/// the old and new value of every field an accessor changes so the change can be undone
+/// and redone. As with `observe`, every field type must be `Clone`.
+pub(crate) fn expand(
+    accessors: &Accessors,
+    generics: &syn::Generics,
+    enumname: &syn::Ident,
+) -> TokenStream {
+    let ident = accessors.ident;
+    let vis = accessors.vis;
+    let history = format_ident!("{}History", ident);
+    let gettersetter = format_ident!("{}GetterSetter", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let clone_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .map(|f| field_bound(f.ty, generics, &quote!(Clone)))
+        .collect();
+    let clone_where = where_with(generics, &clone_bounds);
+    let step = quote!(Vec<(#enumname #ty_generics, #enumname #ty_generics)>);
+
+    quote! {
+        /// Wraps a value and keeps the changes made to its fields so they can be undone.
+        #vis struct #history #generics #where_clause {
+            value: #ident #ty_generics,
+            undo: ::std::collections::VecDeque<#step>,
+            redo: Vec<#step>,
+            capacity: usize,
+            group: Option<#step>,
+        }
+
+        impl #impl_generics #history #ty_generics #clone_where {
+            fn new(value: #ident #ty_generics) -> Self {
+                Self::with_capacity(value, usize::MAX)
+            }
+
+            /// Keeps at most `capacity` undoable steps, forgetting the oldest ones first.
+            fn with_capacity(value: #ident #ty_generics, capacity: usize) -> Self {
+                Self {
+                    value,
+                    undo: ::std::collections::VecDeque::new(),
+                    redo: vec![],
+                    capacity,
+                    group: None,
+                }
+            }
+
+            fn into_inner(self) -> #ident #ty_generics {
+                self.value
+            }
+
+            fn can_undo(&self) -> bool {
+                !self.undo.is_empty() || self.group.as_ref().is_some_and(|group| !group.is_empty())
+            }
+
+            fn can_redo(&self) -> bool {
+                !self.redo.is_empty()
+            }
+
+            fn clear_history(&mut self) {
+                self.undo.clear();
+                self.redo.clear();
+                self.group = None;
+            }
+
+            /// Collects the following changes into a single step, until `end_group`.
+            fn begin_group(&mut self) {
+                self.group.get_or_insert_with(Vec::new);
+            }
+
+            fn end_group(&mut self) {
+                if let Some(step) = self.group.take() {
+                    self.push_step(step);
+                }
+            }
+
+            /// Puts back the values from before the last step. Returns `false` if there
+            /// was nothing to undo. An open group is ended first.
+            fn undo(&mut self) -> bool {
+                self.end_group();
+                let Some(step) = self.undo.pop_back() else {
+                    return false;
+                };
+                // The old values were in the fields before, so restoring them cannot fail.
+                for (old, _) in step.iter().rev() {
+                    let _ = self.value.__replace_field(old.clone());
+                }
+                self.redo.push(step);
+                true
+            }
+
+            /// Applies the last undone step again. Returns `false` if there was nothing
+            /// to redo.
+            fn redo(&mut self) -> bool {
+                let Some(step) = self.redo.pop() else {
+                    return false;
+                };
+                for (_, new) in step.iter() {
+                    let _ = self.value.__replace_field(new.clone());
+                }
+                self.push_step(step);
+                true
+            }
+
+            fn push_step(&mut self, step: #step) {
+                if step.is_empty() || self.capacity == 0 {
+                    return;
+                }
+                while self.undo.len() >= self.capacity {
+                    self.undo.pop_front();
+                }
+                self.undo.push_back(step);
+            }
+
+            /// Runs `change` and records the old and new values of `fields` if it succeeds.
+            /// For a path, the whole field it starts at is recorded.
+            fn edit<__R>(
+                &mut self,
+                fields: &[&str],
+                change: impl FnOnce(&mut #ident #ty_generics) -> Result<__R, ::field_accessor::FieldAccessError>,
+            ) -> Result<__R, ::field_accessor::FieldAccessError> {
+                let fields: Vec<_> = fields.iter().map(|field| <#ident #ty_generics>::__path_field(field)).collect();
+                let old: Vec<_> = fields.iter().map(|field| self.value.getenum(field)).collect();
+                let result = change(&mut self.value)?;
+                let edits = fields
+                    .iter()
+                    .zip(old)
+                    .filter_map(|(field, old)| Some((old.ok()?, self.value.getenum(field).ok()?)));
+                self.redo.clear();
+                match &mut self.group {
+                    Some(group) => group.extend(edits),
+                    None => {
+                        let step = edits.collect();
+                        self.push_step(step);
+                    }
+                }
+                Ok(result)
+            }
+
+            fn get<__T, __M>(&self, field_string: &str) -> Result<&__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.value.get(field_string)
+            }
+
+            fn set<__T, __M>(&mut self, field_string: &str, value: __T) -> Result<(), ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.edit(&[field_string], |inner| inner.set(field_string, value))
+            }
+
+            fn replace<__T, __M>(&mut self, field_string: &str, src: __T) -> Result<__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.edit(&[field_string], |inner| inner.replace(field_string, src))
+            }
+
+            fn take<__T: Default, __M>(&mut self, field_string: &str) -> Result<__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.edit(&[field_string], |inner| inner.take(field_string))
+            }
+
+            fn swap(&mut self, field_string: &str, field_string_y: &str) -> Result<(), ::field_accessor::FieldAccessError> {
+                self.edit(&[field_string, field_string_y], |inner| {
+                    inner.swap(field_string, field_string_y)
+                })
+            }
+        }
+
+        impl #impl_generics ::core::ops::Deref for #history #ty_generics #where_clause {
+            type Target = #ident #ty_generics;
+
+            fn deref(&self) -> &Self::Target {
+                &self.value
+            }
+        }
+    }
+}
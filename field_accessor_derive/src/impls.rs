@@ -0,0 +1,125 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+
+use crate::generics::{field_bound, where_with};
+
+/// A generated enum with one tuple variant per field, such as `(StructName)FieldEnum`.
+///
+/// `#[derive]` would require every field type to implement the derived traits. The
+/// impls generated here are bounded on the field types instead, so they are only
+/// missing for structs whose fields lack the trait.
+pub(crate) struct FieldEnumDef<'a> {
+    pub name: &'a syn::Ident,
+    /// The enum's own generics.
+    pub generics: &'a syn::Generics,
+    /// The derived struct's generics, which decide how field types are bounded.
+    pub struct_generics: &'a syn::Generics,
+    pub variants: &'a [&'a syn::Ident],
+    /// The field types the variants hold, possibly behind a reference.
+    pub tys: &'a [&'a syn::Type],
+    pub has_phantom: bool,
+}
+
+impl FieldEnumDef<'_> {
+    fn bounded_impl(&self, trait_path: TokenStream, body: TokenStream) -> TokenStream {
+        let name = self.name;
+        let (impl_generics, ty_generics, _) = self.generics.split_for_impl();
+        let bounds: Vec<_> = self
+            .tys
+            .iter()
+            .map(|ty| field_bound(ty, self.struct_generics, &trait_path))
+            .collect();
+        let where_clause = where_with(self.generics, &bounds);
+        quote! {
+            impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+                #body
+            }
+        }
+    }
+
+    /// The arm matching the hidden `__Phantom` variant, which can never be reached.
+    fn phantom_arm(&self) -> TokenStream {
+        if self.has_phantom {
+            quote!(Self::__Phantom(_, ref never) => match *never {},)
+        } else {
+            quote!()
+        }
+    }
+
+    pub fn debug(&self) -> TokenStream {
+        let variants = self.variants;
+        let names = variants.iter().map(|v| v.unraw().to_string());
+        let phantom_arm = self.phantom_arm();
+        self.bounded_impl(
+            quote!(::core::fmt::Debug),
+            quote! {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    match *self {
+                        #(Self::#variants(ref value) => f.debug_tuple(#names).field(value).finish(),)*
+                        #phantom_arm
+                    }
+                }
+            },
+        )
+    }
+
+    pub fn partial_eq(&self) -> TokenStream {
+        let variants = self.variants;
+        self.bounded_impl(
+            quote!(::core::cmp::PartialEq),
+            quote! {
+                // Compares fn pointer fields too, as `#[derive(PartialEq)]` would.
+                #[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
+                fn eq(&self, other: &Self) -> bool {
+                    #[allow(unreachable_patterns)]
+                    match (self, other) {
+                        #((Self::#variants(a), Self::#variants(b)) => a == b,)*
+                        _ => false,
+                    }
+                }
+            },
+        )
+    }
+
+    /// Orders by variant first, then by value, like `#[derive(PartialOrd)]`.
+    pub fn partial_ord(&self) -> TokenStream {
+        let variants = self.variants;
+        let positions = 0..variants.len();
+        let phantom_arm = self.phantom_arm();
+        self.bounded_impl(
+            quote!(::core::cmp::PartialOrd),
+            quote! {
+                // Like `eq`, orders fn pointer fields by address.
+                #[allow(unknown_lints, unpredictable_function_pointer_comparisons)]
+                fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                    let position = |value: &Self| match *value {
+                        #(Self::#variants(..) => #positions,)*
+                        #phantom_arm
+                    };
+                    #[allow(unreachable_patterns)]
+                    match (self, other) {
+                        #((Self::#variants(a), Self::#variants(b)) => a.partial_cmp(b),)*
+                        _ => position(self).partial_cmp(&position(other)),
+                    }
+                }
+            },
+        )
+    }
+
+    pub fn clone(&self) -> TokenStream {
+        let variants = self.variants;
+        let phantom_arm = self.phantom_arm();
+        self.bounded_impl(
+            quote!(::core::clone::Clone),
+            quote! {
+                fn clone(&self) -> Self {
+                    match *self {
+                        #(Self::#variants(ref value) => Self::#variants(::core::clone::Clone::clone(value)),)*
+                        #phantom_arm
+                    }
+                }
+            },
+        )
+    }
+}
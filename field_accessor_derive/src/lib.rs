@@ -0,0 +1,587 @@
+mod accessors;
+mod attr;
+mod diff;
+mod dyn_access;
+mod generics;
+mod history;
+mod impls;
+mod model;
+mod observed;
+mod ops;
+mod owned;
+mod path;
+mod refs;
+mod serde;
+mod text;
+mod tracked;
+mod transaction;
+mod validate;
+
+use accessors::Accessors;
+use generics::{field_bound, mentions_generic_param, phantom_variant, where_with};
+use impls::FieldEnumDef;
+use model::{collect_keys, Shape};
+use proc_macro::{self, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput};
+
+#[proc_macro_derive(FieldAccessor, attributes(field_accessor))]
+pub fn derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let DeriveInput {
+        attrs,
+        vis,
+        ident,
+        data,
+        generics,
+    } = input;
+    let container = attr::ContainerAttrs::parse(&attrs)?;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let shapes = match &data {
+        syn::Data::Struct(s) => match &s.fields {
+            syn::Fields::Unit => {
+                return Err(syn::Error::new_spanned(
+                    &ident,
+                    "FieldAccessor cannot be derived for unit structs; only structs with named or tuple fields and enums are supported",
+                ))
+            }
+            fields => vec![Shape::new_struct(fields)?],
+        },
+        syn::Data::Enum(e) => {
+            if e.variants.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    &ident,
+                    "FieldAccessor cannot be derived for enums without variants",
+                ));
+            }
+            e.variants
+                .iter()
+                .map(Shape::new_variant)
+                .collect::<syn::Result<_>>()?
+        }
+        syn::Data::Union(u) => {
+            return Err(syn::Error::new_spanned(
+                u.union_token,
+                "FieldAccessor cannot be derived for unions; only structs with named or tuple fields and enums are supported",
+            ))
+        }
+    };
+    let is_enum = matches!(data, syn::Data::Enum(_));
+
+    let keys = collect_keys(&shapes)?;
+    let variants: Vec<_> = keys.iter().map(|f| &f.variant).collect();
+    let names: Vec<_> = keys.iter().map(|f| &f.name).collect();
+    let tys: Vec<_> = keys.iter().map(|f| f.ty).collect();
+    let enumname = format_ident!("{}{}", ident, "FieldEnum");
+    let enumfields = format_ident!("{}{}", ident, "Fields");
+    let typeslist = format_ident!("{}{}", ident, "TypeList");
+    let enumtypes = format_ident!("{}{}", ident, "Types");
+    let field_count = keys.len();
+    let structinfo = format_ident!("{}{}", ident, "StructInfo");
+    let variantinfo = format_ident!("{}{}", ident, "VariantInfo");
+    let gettersetter = format_ident!("{}{}", ident, "GetterSetter");
+
+    let mut unique_types = vec![];
+    for ty in tys.iter() {
+        if !unique_types.contains(ty) {
+            unique_types.push(*ty);
+        }
+    }
+
+    // `->` goes first, before its `>` is stripped. Whatever is left that cannot be part of
+    // an identifier (e.g. `*` or `+`) becomes `_`, and a clash gets the position as suffix.
+    let mut enumtype_variants: Vec<syn::Ident> = vec![];
+    for (index, ty) in unique_types.iter().enumerate() {
+        let ty_str = quote!(#ty).to_string();
+        let sanitized_body: String = ty_str
+            .replace(' ', "")
+            .replace("->", "Returns")
+            .replace("::", "_")
+            .replace('<', "Of")
+            .replace('>', "")
+            .replace(',', "And")
+            .replace(['(', ')'], "")
+            .replace('[', "ArrayOf")
+            .replace(']', "")
+            .replace(';', "Len")
+            .replace('&', "Ref")
+            .replace('\'', "")
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let mut variant = format_ident!("Type_{}", sanitized_body);
+        if enumtype_variants.contains(&variant) {
+            variant = format_ident!("Type_{}_{}", sanitized_body, index);
+        }
+        enumtype_variants.push(variant);
+    }
+
+    let typeslist_values: Vec<_> = tys
+        .iter()
+        .map(|field_ty| {
+            let index = unique_types.iter().position(|ty| ty == field_ty).unwrap();
+            let variant_ident = &enumtype_variants[index];
+            quote! { #enumtypes::#variant_ident }
+        })
+        .collect();
+
+    // Field types that depend on the struct's generics must not get impls that
+    // could overlap once the generics are instantiated (e.g. `T` and `u32`), so
+    // each of them is tagged with its own marker type.
+    let markers: Vec<_> = unique_types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            if mentions_generic_param(ty, &generics) {
+                let len = i + 1;
+                quote!([(); #len])
+            } else {
+                quote!(())
+            }
+        })
+        .collect();
+
+    // `getenum` clones every field, so it needs every field type to be `Clone`.
+    let clone_bounds: Vec<_> = unique_types
+        .iter()
+        .map(|ty| field_bound(ty, &generics, &quote!(Clone)))
+        .collect();
+    let getenum_where = if clone_bounds.is_empty() {
+        quote!()
+    } else {
+        quote!(where #(#clone_bounds),*)
+    };
+
+    let reflect_where = where_with(&generics, &clone_bounds);
+
+    let phantom = phantom_variant(&generics, &tys);
+    let enum_def = FieldEnumDef {
+        name: &enumname,
+        generics: &generics,
+        struct_generics: &generics,
+        variants: &variants,
+        tys: &tys,
+        has_phantom: !phantom.is_empty(),
+    };
+    let phantom_arm = if phantom.is_empty() {
+        quote!()
+    } else {
+        quote!(#enumname::__Phantom(_, never) => match *never {},)
+    };
+    let enum_impls = [
+        enum_def.debug(),
+        enum_def.partial_eq(),
+        enum_def.partial_ord(),
+        enum_def.clone(),
+    ];
+
+    let accessors = Accessors {
+        ident: &ident,
+        vis: &vis,
+        shapes: &shapes,
+        keys: &keys,
+        invariant: container.invariant.as_ref(),
+    };
+
+    // Field names containing `[` or `.` are resolved as paths, so on generic types the
+    // accessors take the `'static` bounds of `*_path`. Types with lifetime parameters are
+    // left out, as they are rarely `'static`. The values inside sequence and map fields get
+    // accessors too, under a marker of their own so they do not clash with a field type
+    // that is spelled differently.
+    let routes_paths = generics.lifetimes().next().is_none();
+    let accessor_where = if routes_paths {
+        where_with(&generics, &path::bounds(&accessors, &generics))
+    } else {
+        quote!(#where_clause)
+    };
+    let mut element_types = vec![];
+    if routes_paths {
+        for ty in unique_types.iter() {
+            if let Some(element) = path::element_type(ty) {
+                if !unique_types.contains(&element) && !element_types.contains(&element) {
+                    element_types.push(element);
+                }
+            }
+        }
+    }
+    let accessor_types: Vec<_> = unique_types.iter().chain(element_types.iter()).collect();
+    let accessor_markers: Vec<_> = markers
+        .iter()
+        .cloned()
+        .chain(element_types.iter().enumerate().map(|(i, ty)| {
+            if mentions_generic_param(ty, &generics) {
+                let len = unique_types.len() + i + 1;
+                quote!([(); #len])
+            } else {
+                quote!([(); 0])
+            }
+        }))
+        .collect();
+    let route = |call: proc_macro2::TokenStream| {
+        if !routes_paths {
+            return quote!();
+        }
+        quote! {
+            if field_string.contains('[')
+                || (field_string.contains('.') && ![#(#names),*].contains(&field_string))
+            {
+                return #call;
+            }
+        }
+    };
+    let get_route = route(quote!(self.get_path(field_string)));
+    let get_mut_route = route(quote!(self.get_path_mut(field_string)));
+    let take_route = route(quote!(self.take_path(field_string)));
+    let replace_route = route(quote!(self.__replace_path(field_string, src)));
+    let set_route = route(quote!(self.set_path(field_string, value)));
+
+    let mut get_quotes = vec![];
+    let mut get_mut_quotes = vec![];
+    let mut take_quotes = vec![];
+    let mut replace_quotes = vec![];
+    let mut set_quotes = vec![];
+    let mut field_enum_quotes = vec![];
+    for ty in accessor_types.iter() {
+        get_quotes.push(accessors.match_field(Some(ty), false, |f| {
+            let binding = &f.binding;
+            quote!(Ok(#binding))
+        }));
+        get_mut_quotes.push(accessors.match_field(Some(ty), true, |f| {
+            let binding = &f.binding;
+            quote!(Ok(#binding))
+        }));
+        // `take` and `replace` hand the previous value back, so if the invariant fails
+        // they put it back through a second match instead of `__replace_field`.
+        let restore = accessors.match_field(Some(ty), true, |f| {
+            let binding = &f.binding;
+            quote!({ *#binding = previous; Ok(()) })
+        });
+        let take = accessors.match_field(Some(ty), true, |f| {
+            let binding = &f.binding;
+            let validate = accessors.validate(f, quote!(&value));
+            quote!({
+                let value = Default::default();
+                #validate
+                Ok(std::mem::replace(#binding, value))
+            })
+        });
+        let replace = accessors.match_field(Some(ty), true, |f| {
+            let binding = &f.binding;
+            let validate = accessors.validate(f, quote!(&src));
+            quote!({ #validate Ok(std::mem::replace(#binding, src)) })
+        });
+        if accessors.invariant.is_some() {
+            let check = accessors.check_invariant(quote!(#restore?;));
+            take_quotes.push(quote!(let previous = #take?; #check Ok(previous)));
+            replace_quotes.push(quote!(let previous = #replace?; #check Ok(previous)));
+        } else {
+            take_quotes.push(take);
+            replace_quotes.push(replace);
+        }
+        set_quotes.push(
+            accessors.guard(
+                accessors.match_field(Some(ty), true, |f| accessors.assign(f, quote!(value))),
+            ),
+        );
+        field_enum_quotes.push(accessors.match_field(Some(ty), true, |f| {
+            let variant = &f.variant;
+            quote!(Ok(#enumname::#variant(value)))
+        }));
+    }
+    let getenum_quote = accessors.match_field(None, false, |f| {
+        let binding = &f.binding;
+        let variant = &f.variant;
+        quote!(Ok(#enumname::#variant(#binding.clone())))
+    });
+    // Swapping back undoes a swap that breaks the invariant. The values are going back to
+    // where they were, so they are not validated again.
+    let swap = accessors.swap(true);
+    let swap_back = accessors.swap(false);
+    let swap_check = accessors.check_invariant(quote!(#swap_back?;));
+    let swap_quote = if accessors.invariant.is_some() {
+        quote!(#swap?; #swap_check Ok(()))
+    } else {
+        swap
+    };
+    let path_items = path::expand(&accessors, &generics, &enumname);
+    let ref_items = refs::expand(&accessors, &generics);
+    let diff_items = diff::expand(&accessors, &generics);
+    let validate_items = validate::expand(&accessors, &generics);
+    let observed_items = if container.observe {
+        observed::expand(&accessors, &generics, &enumname)
+    } else {
+        quote!()
+    };
+    let history_items = if container.history {
+        history::expand(&accessors, &generics, &enumname)
+    } else {
+        quote!()
+    };
+    let tracked_items = if container.track_changes {
+        tracked::expand(&accessors, &generics)
+    } else {
+        quote!()
+    };
+    let transaction_items = transaction::expand(&accessors, &generics, &enumname);
+    let dyn_items = dyn_access::expand(&accessors, &generics);
+    let text_items = text::expand(&accessors, &generics);
+    let owned_items = owned::expand(&accessors, &generics, &enumname, !phantom.is_empty());
+    let serde_items = serde::expand(&accessors, &generics);
+    let field_enum_serde = serde::field_enum_attrs(&tys, &generics);
+    let op_items = ops::expand(&accessors, &generics, &enumname);
+    let fields_serde = serde::fields_attrs();
+    let variant_serde = serde::variant_attrs(&names);
+    let phantom_serde = serde::phantom_attrs(&phantom);
+
+    let variant_items = if is_enum {
+        let variant_names: Vec<_> = shapes
+            .iter()
+            .map(|shape| shape.variant.unwrap().to_string())
+            .collect();
+        let patterns: Vec<_> = shapes
+            .iter()
+            .map(|shape| shape.pattern(|_| false))
+            .collect();
+        let variant_field_names: Vec<_> = shapes.iter().map(|shape| shape.names()).collect();
+        let variant_field_types: Vec<Vec<_>> = shapes
+            .iter()
+            .map(|shape| shape.fields.iter().map(|f| f.ty).collect())
+            .collect();
+        quote! {
+            #[derive(Debug, Clone)]
+            #vis struct #variantinfo {
+                variant_name: String,
+                field_names: Vec<String>,
+                field_types: Vec<String>,
+            }
+
+            impl #impl_generics #ident #ty_generics #where_clause {
+                fn variant_name(&self) -> &'static str {
+                    match self {
+                        #(#patterns => #variant_names,)*
+                    }
+                }
+
+                fn getvariantinfo(&self) -> #variantinfo {
+                    let variant_name = self.variant_name();
+                    self.getvariantinfos()
+                        .into_iter()
+                        .find(|info| info.variant_name == variant_name)
+                        .unwrap()
+                }
+
+                fn getvariantinfos(&self) -> Vec<#variantinfo> {
+                    vec![#(
+                        #variantinfo {
+                            variant_name: #variant_names.to_string(),
+                            field_names: vec![#(#variant_field_names.to_string()),*],
+                            field_types: vec![#(stringify!(#variant_field_types).to_string()),*],
+                        }
+                    ),*]
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+
+        #[derive(Debug, Clone)]
+        #vis struct #structinfo {
+            field_names: Vec<String>,
+            field_types: Vec<String>,
+            struct_name: String
+        }
+
+        // `Reflect::FieldEnum` names the enum, so it is as visible as the derived type, while
+        // the field types may be less visible.
+        #field_enum_serde
+        #[allow(non_camel_case_types, unknown_lints, private_interfaces)]
+        #vis enum #enumname #generics #where_clause {
+            #(#variant_serde #variants(#tys),)*
+            #phantom_serde
+            #phantom
+        }
+
+        #(#enum_impls)*
+
+        impl #impl_generics #enumname #ty_generics #where_clause {
+            fn field_name(&self) -> &'static str {
+                match self {
+                    #(#enumname::#variants(_) => #names,)*
+                    #phantom_arm
+                }
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        #[allow(non_camel_case_types)]
+        pub enum #enumtypes {
+            #(#enumtype_variants),*
+        }
+
+        #[derive(
+            ::field_accessor::__private::strum::EnumString,
+            ::field_accessor::__private::strum::AsRefStr,
+            ::field_accessor::__private::strum::EnumIter,
+            PartialOrd, Ord, Hash, Clone, Eq, PartialEq, Debug
+        )]
+        #[strum(crate = "::field_accessor::__private::strum")]
+        #fields_serde
+        #[allow(non_camel_case_types)]
+        pub enum #enumfields{
+            #(
+                #[strum(serialize = #names)]
+                #variant_serde
+                #variants
+            ),*
+        }
+
+        #[allow(non_upper_case_globals)]
+        pub const #typeslist: [#enumtypes; #field_count] = [
+            #(#typeslist_values),*
+        ];
+
+        trait #gettersetter<T, M = ()> {
+            fn get(&self, field_string: &str) -> Result<&T, ::field_accessor::FieldAccessError>;
+            fn get_mut(&mut self, field_string: &str) -> Result<&mut T, ::field_accessor::FieldAccessError>;
+            fn take(&mut self, field_string: &str) -> Result<T, ::field_accessor::FieldAccessError> where T: Default;
+            fn replace(&mut self, field_string: &str, src: T) -> Result<T, ::field_accessor::FieldAccessError>;
+            fn set(&mut self, field_string: &str, value: T) -> Result<(), ::field_accessor::FieldAccessError>;
+            #[doc(hidden)]
+            type __FieldEnum;
+            /// Wraps `value` as the named field without assigning it.
+            #[doc(hidden)]
+            fn __field_enum(&self, field_string: &str, value: T) -> Result<Self::__FieldEnum, ::field_accessor::FieldAccessError>;
+        }
+
+        #(
+            impl #impl_generics #gettersetter<#accessor_types, #accessor_markers> for #ident #ty_generics #accessor_where {
+                fn get(&self, field_string: &str) -> Result<&#accessor_types, ::field_accessor::FieldAccessError> {
+                    #get_route
+                    #get_quotes
+                }
+                fn get_mut(&mut self, field_string: &str) -> Result<&mut #accessor_types, ::field_accessor::FieldAccessError> {
+                    #get_mut_route
+                    #get_mut_quotes
+                }
+                fn take(&mut self, field_string: &str) -> Result<#accessor_types, ::field_accessor::FieldAccessError> where for<'__fa> #accessor_types: Default {
+                    #take_route
+                    #take_quotes
+                }
+                fn replace(&mut self, field_string: &str, src: #accessor_types) -> Result<#accessor_types, ::field_accessor::FieldAccessError> {
+                    #replace_route
+                    #replace_quotes
+                }
+
+                fn set(&mut self, field_string: &str, value: #accessor_types) -> Result<(), ::field_accessor::FieldAccessError>{
+                    #set_route
+                    #set_quotes
+                }
+
+                type __FieldEnum = #enumname #ty_generics;
+
+                fn __field_enum(&self, field_string: &str, value: #accessor_types) -> Result<Self::__FieldEnum, ::field_accessor::FieldAccessError> {
+                    #field_enum_quotes
+                }
+            }
+        )*
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+
+            fn swap(&mut self, field_string: &str, field_string_y: &str) -> Result<(), ::field_accessor::FieldAccessError> {
+                #swap_quote
+            }
+
+            fn getenum(&self, field_string: &str) -> Result<#enumname #ty_generics, ::field_accessor::FieldAccessError> #getenum_where {
+                #getenum_quote
+            }
+
+            /// The field that `field_string`, a field name or a path, starts at.
+            #[doc(hidden)]
+            fn __path_field(field_string: &str) -> &str {
+                ::field_accessor::__private::path_field(field_string, &[#(#names),*])
+            }
+
+            fn getstructinfo(&self) -> #structinfo {
+                #structinfo {
+                    field_names: vec![#(#names.to_string()),*],
+                    field_types: vec![#(stringify!(#tys).to_string()),*],
+                    struct_name: stringify!(#ident).to_string()}
+            }
+        }
+
+        impl #impl_generics ::field_accessor::Reflect for #ident #ty_generics #reflect_where {
+            type FieldEnum = #enumname #ty_generics;
+            type Fields = #enumfields;
+            type Types = #enumtypes;
+            type StructInfo = #structinfo;
+
+            fn struct_name() -> &'static str {
+                stringify!(#ident)
+            }
+
+            fn field_names() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn field_types() -> &'static [#enumtypes] {
+                &#typeslist
+            }
+
+            fn getenum(&self, field_string: &str) -> Result<Self::FieldEnum, ::field_accessor::FieldAccessError> {
+                Self::getenum(self, field_string)
+            }
+
+            fn setenum(&mut self, value: Self::FieldEnum) -> Result<(), ::field_accessor::FieldAccessError> {
+                Self::setenum(self, value)
+            }
+
+            fn getstructinfo(&self) -> Self::StructInfo {
+                Self::getstructinfo(self)
+            }
+        }
+
+        #variant_items
+
+        #path_items
+
+        #ref_items
+
+        #diff_items
+
+        #validate_items
+
+        #dyn_items
+
+        #text_items
+
+        #owned_items
+
+        #transaction_items
+
+        #op_items
+
+        #serde_items
+
+        #tracked_items
+
+        #observed_items
+
+        #history_items
+    })
+}
@@ -0,0 +1,174 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+
+use crate::attr::{FieldAttrs, Validator};
+
+/// A field of the derived type, together with the names it is exposed under.
+pub(crate) struct FieldInfo<'a> {
+    /// The field's name or index, as written in a struct pattern.
+    pub member: syn::Member,
+    /// The string the field is looked up by at runtime.
+    pub name: String,
+    /// The variant representing the field in the generated enums.
+    pub variant: syn::Ident,
+    /// The local the field is bound to when `self` is destructured.
+    pub binding: syn::Ident,
+    pub ty: &'a syn::Type,
+    /// Whether `#[field_accessor(readonly)]` forbids modifying the field.
+    pub readonly: bool,
+    /// Whether `#[field_accessor(nested)]` lets paths continue into the field.
+    pub nested: bool,
+    /// Whether `#[field_accessor(no_parse)]` excludes the field from `set_from_str`.
+    pub no_parse: bool,
+    /// Whether `#[field_accessor(debug_fmt)]` formats the field with `Debug`.
+    pub debug_fmt: bool,
+    /// The checks a new value must pass before it is assigned.
+    pub validators: Vec<Validator>,
+}
+
+/// A struct, or one variant of an enum, whose fields can be accessed.
+pub(crate) struct Shape<'a> {
+    /// `Self` for a struct, `Self::Variant` for an enum variant.
+    pub path: TokenStream,
+    /// The variant's name, or `None` for a struct.
+    pub variant: Option<&'a syn::Ident>,
+    pub fields: Vec<FieldInfo<'a>>,
+    /// The fields marked `#[field_accessor(skip)]`.
+    pub skipped: Vec<(syn::Member, &'a syn::Type)>,
+}
+
+impl<'a> Shape<'a> {
+    pub fn new_struct(fields: &'a syn::Fields) -> syn::Result<Self> {
+        let (fields, skipped) = collect_fields(fields, None)?;
+        Ok(Shape {
+            path: quote!(Self),
+            variant: None,
+            fields,
+            skipped,
+        })
+    }
+
+    pub fn new_variant(variant: &'a syn::Variant) -> syn::Result<Self> {
+        crate::attr::reject(&variant.attrs, "enum variants")?;
+        let ident = &variant.ident;
+        let (fields, skipped) = collect_fields(&variant.fields, Some(ident))?;
+        Ok(Shape {
+            path: quote!(Self::#ident),
+            variant: Some(ident),
+            fields,
+            skipped,
+        })
+    }
+
+    /// A pattern matching this shape that binds the fields accepted by `bind`.
+    pub fn pattern(&self, bind: impl Fn(&FieldInfo) -> bool) -> TokenStream {
+        let path = &self.path;
+        let (members, bindings): (Vec<_>, Vec<_>) = self
+            .fields
+            .iter()
+            .filter(|f| bind(f))
+            .map(|f| (&f.member, &f.binding))
+            .unzip();
+        quote!(#path { #(#members: #bindings,)* .. })
+    }
+
+    pub fn names(&self) -> Vec<&String> {
+        self.fields.iter().map(|f| &f.name).collect()
+    }
+}
+
+type Collected<'a> = (Vec<FieldInfo<'a>>, Vec<(syn::Member, &'a syn::Type)>);
+
+/// Fields of tuple variants are named after their variant, e.g. `Resize.0`, so that the
+/// positions of different variants can have different types.
+fn collect_fields<'a>(
+    fields: &'a syn::Fields,
+    variant: Option<&syn::Ident>,
+) -> syn::Result<Collected<'a>> {
+    let mut collected: Vec<FieldInfo> = vec![];
+    let mut skipped = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        let (member, name, variant) = match &field.ident {
+            Some(ident) => (
+                syn::Member::Named(ident.clone()),
+                ident.unraw().to_string(),
+                ident.clone(),
+            ),
+            None => match variant {
+                Some(variant) => (
+                    syn::Member::Unnamed(syn::Index::from(i)),
+                    format!("{}.{}", variant.unraw(), i),
+                    format_ident!("{}_{}", variant, i),
+                ),
+                None => (
+                    syn::Member::Unnamed(syn::Index::from(i)),
+                    i.to_string(),
+                    format_ident!("_{}", i),
+                ),
+            },
+        };
+        if attrs.skip {
+            skipped.push((member, &field.ty));
+            continue;
+        }
+        // A renamed field keeps its own variant ident if the new name is not a valid one.
+        let (name, variant) = match attrs.rename {
+            Some(rename) => {
+                let variant = syn::parse_str::<syn::Ident>(&rename).unwrap_or(variant);
+                (rename, variant)
+            }
+            None => (name, variant),
+        };
+        if collected.iter().any(|f| f.name == name) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("another field is already accessed as `{}`", name),
+            ));
+        }
+        collected.push(FieldInfo {
+            member,
+            binding: format_ident!("__field_{}", i),
+            name,
+            variant,
+            ty: &field.ty,
+            readonly: attrs.readonly,
+            nested: attrs.nested,
+            no_parse: attrs.no_parse,
+            debug_fmt: attrs.debug_fmt,
+            validators: attrs.validators,
+        });
+    }
+    Ok((collected, skipped))
+}
+
+/// Returns the distinct fields across all shapes, in order of first appearance.
+///
+/// Fields of different enum variants that share a name are exposed as one field,
+/// so they must also share a type.
+pub(crate) fn collect_keys<'s, 'a>(shapes: &'s [Shape<'a>]) -> syn::Result<Vec<&'s FieldInfo<'a>>> {
+    let mut keys: Vec<(&Shape, &FieldInfo)> = vec![];
+    for shape in shapes {
+        for field in shape.fields.iter() {
+            match keys.iter().find(|(_, key)| key.name == field.name) {
+                Some((first, key)) if key.ty != field.ty => {
+                    let first_ty = key.ty;
+                    let first_variant = first.variant;
+                    return Err(syn::Error::new_spanned(
+                        field.ty,
+                        format!(
+                            "field `{}` has type `{}` in variant `{}`; fields that share a name must share a type",
+                            field.name,
+                            quote!(#first_ty),
+                            quote!(#first_variant),
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => keys.push((shape, field)),
+            }
+        }
+    }
+    Ok(keys.into_iter().map(|(_, key)| key).collect())
+}
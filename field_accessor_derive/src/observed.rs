@@ -0,0 +1,191 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::{field_bound, where_with};
+
+/// Generates `(StructName)Observed` for `#[field_accessor(observe)]`, a wrapper that calls
+/// the registered listeners with the old and new value whenever an accessor changes a
+/// field, and the op listeners with the change as a `(StructName)Op`. The values are
+/// cloned into `(StructName)FieldEnum`, so every field type must be `Clone`.
+pub(crate) fn expand(
+    accessors: &Accessors,
+    generics: &syn::Generics,
+    enumname: &syn::Ident,
+) -> TokenStream {
+    let ident = accessors.ident;
+    let vis = accessors.vis;
+    let observed = format_ident!("{}Observed", ident);
+    let opname = format_ident!("{}Op", ident);
+    let gettersetter = format_ident!("{}GetterSetter", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let names: Vec<_> = accessors.keys.iter().map(|f| &f.name).collect();
+    let clone_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .map(|f| field_bound(f.ty, generics, &quote!(Clone)))
+        .collect();
+    let clone_where = where_with(generics, &clone_bounds);
+    let listener = quote!(dyn Fn(&str, &#enumname #ty_generics, &#enumname #ty_generics));
+
+    quote! {
+        /// Wraps a value and calls listeners whenever one of its fields is changed.
+        #vis struct #observed #generics #where_clause {
+            value: #ident #ty_generics,
+            listeners: Vec<(Option<&'static str>, Box<#listener>)>,
+            op_listeners: Vec<Box<dyn Fn(&#opname #ty_generics)>>,
+        }
+
+        impl #impl_generics #observed #ty_generics #clone_where {
+            fn new(value: #ident #ty_generics) -> Self {
+                Self {
+                    value,
+                    listeners: vec![],
+                    op_listeners: vec![],
+                }
+            }
+
+            fn into_inner(self) -> #ident #ty_generics {
+                self.value
+            }
+
+            /// Registers `listener` for changes to `field_string`, or to every field if it
+            /// is `None`. It is called with the field name and the old and new values.
+            fn on_change(
+                &mut self,
+                field_string: Option<&str>,
+                listener: impl Fn(&str, &#enumname #ty_generics, &#enumname #ty_generics) + 'static,
+            ) -> Result<(), ::field_accessor::FieldAccessError> {
+                let field = match field_string {
+                    Some(field_string) => Some(
+                        [#(#names),*]
+                            .into_iter()
+                            .find(|name| *name == field_string)
+                            .ok_or_else(|| ::field_accessor::FieldAccessError::UnknownField {
+                                struct_name: stringify!(#ident),
+                                field: field_string.to_string(),
+                            })?,
+                    ),
+                    None => None,
+                };
+                self.listeners.push((field, Box::new(listener)));
+                Ok(())
+            }
+
+            /// Registers `listener` for every change, which it is given as an op that
+            /// `apply_op` can replay. A change made through a path is given as a `Set` of
+            /// the field the path starts at.
+            fn on_op(&mut self, listener: impl Fn(&#opname #ty_generics) + 'static) {
+                self.op_listeners.push(Box::new(listener));
+            }
+
+            /// The field's current value, if any listener wants to hear about it. Given a
+            /// path, the value of the field it starts at.
+            fn observe(&self, field_string: &str) -> Option<#enumname #ty_generics> {
+                let field_string = <#ident #ty_generics>::__path_field(field_string);
+                self.listeners
+                    .iter()
+                    .any(|(field, _)| field.map_or(true, |field| field == field_string))
+                    .then(|| self.value.getenum(field_string).ok())
+                    .flatten()
+            }
+
+            fn notify(&self, field_string: &str, old: Option<#enumname #ty_generics>) {
+                let Some(old) = old else {
+                    return;
+                };
+                let field_string = <#ident #ty_generics>::__path_field(field_string);
+                let Ok(new) = self.value.getenum(field_string) else {
+                    return;
+                };
+                for (field, listener) in self.listeners.iter() {
+                    if field.map_or(true, |field| field == field_string) {
+                        listener(field_string, &old, &new);
+                    }
+                }
+            }
+
+            /// Calls the op listeners with the op that `op` builds, if there are any.
+            fn record(&self, op: impl FnOnce(&Self) -> Option<#opname #ty_generics>) {
+                if self.op_listeners.is_empty() {
+                    return;
+                }
+                if let Some(op) = op(self) {
+                    for listener in self.op_listeners.iter() {
+                        listener(&op);
+                    }
+                }
+            }
+
+            /// The op that sets the field `field_string` starts at to its current value.
+            fn set_op(&self, field_string: &str) -> Option<#opname #ty_generics> {
+                let field_string = <#ident #ty_generics>::__path_field(field_string);
+                self.value.getenum(field_string).ok().map(#opname::set)
+            }
+
+            fn get<__T, __M>(&self, field_string: &str) -> Result<&__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.value.get(field_string)
+            }
+
+            fn set<__T, __M>(&mut self, field_string: &str, value: __T) -> Result<(), ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                let old = self.observe(field_string);
+                self.value.set(field_string, value)?;
+                self.notify(field_string, old);
+                self.record(|observed| observed.set_op(field_string));
+                Ok(())
+            }
+
+            fn replace<__T, __M>(&mut self, field_string: &str, src: __T) -> Result<__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                let old = self.observe(field_string);
+                let previous = self.value.replace(field_string, src)?;
+                self.notify(field_string, old);
+                self.record(|observed| observed.set_op(field_string));
+                Ok(previous)
+            }
+
+            fn take<__T: Default, __M>(&mut self, field_string: &str) -> Result<__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                let old = self.observe(field_string);
+                let previous = self.value.take(field_string)?;
+                self.notify(field_string, old);
+                self.record(|observed| {
+                    if <#ident #ty_generics>::__path_field(field_string) == field_string {
+                        Some(#opname::take(field_string))
+                    } else {
+                        observed.set_op(field_string)
+                    }
+                });
+                Ok(previous)
+            }
+
+            fn swap(&mut self, field_string: &str, field_string_y: &str) -> Result<(), ::field_accessor::FieldAccessError> {
+                let old = self.observe(field_string);
+                let old_y = self.observe(field_string_y);
+                self.value.swap(field_string, field_string_y)?;
+                self.notify(field_string, old);
+                self.notify(field_string_y, old_y);
+                self.record(|_| Some(#opname::swap(field_string, field_string_y)));
+                Ok(())
+            }
+        }
+
+        impl #impl_generics ::core::ops::Deref for #observed #ty_generics #where_clause {
+            type Target = #ident #ty_generics;
+
+            fn deref(&self) -> &Self::Target {
+                &self.value
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::{field_bound, mentions_generic_param, where_with};
+use crate::serde;
+
+/// Generates `(StructName)Op`, a mutation by field name that can be stored and replayed
+/// with `apply_op` and `apply_ops`. As with `(StructName)FieldEnum`, its `Debug`, `Clone`
+/// and `PartialEq` impls are bounded on the field types.
+pub(crate) fn expand(
+    accessors: &Accessors,
+    generics: &syn::Generics,
+    enumname: &syn::Ident,
+) -> TokenStream {
+    let ident = accessors.ident;
+    let vis = accessors.vis;
+    let opname = format_ident!("{}Op", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let tys: Vec<_> = accessors.keys.iter().map(|f| f.ty).collect();
+    let (serde_attrs, value_serde) = serde::op_attrs(&tys, generics);
+    let bounded = |bound: TokenStream| {
+        let bounds: Vec<_> = accessors
+            .keys
+            .iter()
+            .map(|f| field_bound(f.ty, generics, &bound))
+            .collect();
+        where_with(generics, &bounds)
+    };
+    let debug_where = bounded(quote!(::core::fmt::Debug));
+    let clone_where = bounded(quote!(::core::clone::Clone));
+    let eq_where = bounded(quote!(::core::cmp::PartialEq));
+    // Replaying `Take` puts the default value in the field, or fails if its type has none.
+    // Without the bound, taking a generic field would always fail.
+    let default_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .filter(|f| mentions_generic_param(f.ty, generics))
+        .map(|f| field_bound(f.ty, generics, &quote!(::core::default::Default)))
+        .collect();
+    let default_where = where_with(generics, &default_bounds);
+    // Like `take`, replaying it checks the validators and the invariant.
+    let take = if accessors.invariant.is_some() {
+        let check = accessors.check_invariant(quote!(self.__replace_field(previous)?;));
+        quote! {
+            let previous = self.__replace_field(value)?;
+            #check
+            Ok(())
+        }
+    } else {
+        quote!(self.__replace_field(value).map(drop))
+    };
+    let take_arms = accessors.keys.iter().map(|f| {
+        let name = &f.name;
+        let variant = &f.variant;
+        let ty = f.ty;
+        let validate = accessors.validate(f, quote!(&value));
+        quote! {
+            #name => {
+                let value = ::field_accessor::__private::Defaulted::<#ty>(::core::marker::PhantomData).default_value(&field)?;
+                #validate
+                #enumname::#variant(value)
+            }
+        }
+    });
+
+    quote! {
+        /// A change to a field, which `apply_op` performs.
+        #serde_attrs
+        #vis enum #opname #generics #where_clause {
+            Set {
+                field: String,
+                #value_serde
+                value: #enumname #ty_generics,
+            },
+            Swap {
+                a: String,
+                b: String,
+            },
+            Take {
+                field: String,
+            },
+        }
+
+        impl #impl_generics #opname #ty_generics #where_clause {
+            fn set(value: #enumname #ty_generics) -> Self {
+                Self::Set {
+                    field: value.field_name().to_string(),
+                    value,
+                }
+            }
+
+            fn swap(a: &str, b: &str) -> Self {
+                Self::Swap {
+                    a: a.to_string(),
+                    b: b.to_string(),
+                }
+            }
+
+            fn take(field: &str) -> Self {
+                Self::Take {
+                    field: field.to_string(),
+                }
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Debug for #opname #ty_generics #debug_where {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    Self::Set { field, value } => f.debug_struct("Set").field("field", field).field("value", value).finish(),
+                    Self::Swap { a, b } => f.debug_struct("Swap").field("a", a).field("b", b).finish(),
+                    Self::Take { field } => f.debug_struct("Take").field("field", field).finish(),
+                }
+            }
+        }
+
+        impl #impl_generics ::core::clone::Clone for #opname #ty_generics #clone_where {
+            fn clone(&self) -> Self {
+                match self {
+                    Self::Set { field, value } => Self::Set {
+                        field: field.clone(),
+                        value: value.clone(),
+                    },
+                    Self::Swap { a, b } => Self::Swap {
+                        a: a.clone(),
+                        b: b.clone(),
+                    },
+                    Self::Take { field } => Self::Take {
+                        field: field.clone(),
+                    },
+                }
+            }
+        }
+
+        impl #impl_generics ::core::cmp::PartialEq for #opname #ty_generics #eq_where {
+            fn eq(&self, other: &Self) -> bool {
+                match (self, other) {
+                    (Self::Set { field, value }, Self::Set { field: field_y, value: value_y }) => {
+                        field == field_y && value == value_y
+                    }
+                    (Self::Swap { a, b }, Self::Swap { a: a_y, b: b_y }) => a == a_y && b == b_y,
+                    (Self::Take { field }, Self::Take { field: field_y }) => field == field_y,
+                    _ => false,
+                }
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #default_where {
+            /// Performs `op` like `setenum`, `swap` or `take` would.
+            fn apply_op(&mut self, op: #opname #ty_generics) -> Result<(), ::field_accessor::FieldAccessError> {
+                match op {
+                    #opname::Set { field, value } => {
+                        if value.field_name() != field {
+                            return Err(::field_accessor::FieldAccessError::FieldMismatch {
+                                field,
+                                found: value.field_name(),
+                            });
+                        }
+                        self.setenum(value)
+                    }
+                    #opname::Swap { a, b } => self.swap(&a, &b),
+                    #opname::Take { field } => {
+                        use ::field_accessor::__private::{DefaultFallback as _, DefaultValue as _};
+                        let value = match field.as_str() {
+                            #(#take_arms)*
+                            _ => {
+                                return Err(::field_accessor::FieldAccessError::UnknownField {
+                                    struct_name: stringify!(#ident),
+                                    field,
+                                })
+                            }
+                        };
+                        #take
+                    }
+                }
+            }
+
+            /// Performs `ops` in order, stopping at the first one that fails. The ones
+            /// before it stay applied.
+            fn apply_ops(&mut self, ops: impl IntoIterator<Item = #opname #ty_generics>) -> Result<(), ::field_accessor::FieldAccessError> {
+                for op in ops {
+                    self.apply_op(op)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
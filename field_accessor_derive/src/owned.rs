@@ -0,0 +1,175 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::accessors::Accessors;
+use crate::generics::field_bound;
+
+/// Generates `setenum`, `into_fields` and, for structs, `try_from_fields`, which move
+/// `(StructName)FieldEnum` values into and out of the derived type, and the hidden
+/// `__replace_field`, which assigns a value without checking it, e.g. to put back the
+/// previous one after a change broke the invariant.
+pub(crate) fn expand(
+    accessors: &Accessors,
+    generics: &syn::Generics,
+    enumname: &syn::Ident,
+    has_phantom: bool,
+) -> TokenStream {
+    let ident = accessors.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let phantom_arm = if has_phantom {
+        quote!(#enumname::__Phantom(_, never) => match never {},)
+    } else {
+        quote!()
+    };
+
+    // With `unchecked`, the arms skip the validators and evaluate to the previous value.
+    let setenum_arms = |unchecked: bool| -> Vec<TokenStream> {
+        accessors
+            .shapes
+            .iter()
+            .map(|shape| {
+                let pattern = shape.pattern(|f| !f.readonly);
+                let arms = accessors.keys.iter().map(|key| {
+                    let variant = &key.variant;
+                    let name = &key.name;
+                    match shape.fields.iter().find(|f| f.name == key.name) {
+                        Some(f) if f.readonly => quote! {
+                            #enumname::#variant(_) => Err(::field_accessor::FieldAccessError::ReadOnlyField {
+                                field: #name.to_string(),
+                            }),
+                        },
+                        Some(f) if unchecked => {
+                            let binding = &f.binding;
+                            quote! {
+                                #enumname::#variant(value) => {
+                                    Ok(#enumname::#variant(std::mem::replace(#binding, value)))
+                                }
+                            }
+                        }
+                        Some(f) => {
+                            let assign = accessors.assign(f, quote!(value));
+                            quote!(#enumname::#variant(value) => #assign)
+                        }
+                        None => {
+                            let missing = accessors.missing_field(shape, &quote!(#name));
+                            quote!(#enumname::#variant(_) => Err(#missing),)
+                        }
+                    }
+                });
+                quote! {
+                    #pattern => match value {
+                        #(#arms)*
+                        #phantom_arm
+                    }
+                }
+            })
+            .collect()
+    };
+
+    let into_fields_arms = accessors.shapes.iter().map(|shape| {
+        let pattern = shape.pattern(|_| true);
+        let variants = shape.fields.iter().map(|f| &f.variant);
+        let bindings = shape.fields.iter().map(|f| &f.binding);
+        quote!(#pattern => vec![#(#enumname::#variants(#bindings)),*],)
+    });
+
+    let try_from_fields = match accessors.shapes {
+        [shape] if shape.variant.is_none() => {
+            let bindings: Vec<_> = shape.fields.iter().map(|f| &f.binding).collect();
+            let variants = shape.fields.iter().map(|f| &f.variant);
+            let names: Vec<_> = shape.fields.iter().map(|f| &f.name).collect();
+            let tys = shape.fields.iter().map(|f| f.ty);
+            let members = shape.fields.iter().map(|f| &f.member);
+            let check = accessors
+                .invariant
+                .map(|_| quote!(value.__check_invariant()?;));
+            let validates = shape.fields.iter().map(|f| {
+                let binding = &f.binding;
+                let validate = accessors.validate(f, quote!(value));
+                quote! {
+                    if let Some(value) = &#binding {
+                        #validate
+                    }
+                }
+            });
+            let (skipped_members, skipped_tys): (Vec<_>, Vec<_>) = shape
+                .skipped
+                .iter()
+                .map(|(member, ty)| (member, *ty))
+                .unzip();
+            // Skipped fields have no value in the enum and start out as their default.
+            let default_bounds: Vec<_> = skipped_tys
+                .iter()
+                .map(|ty| field_bound(ty, generics, &quote!(Default)))
+                .collect();
+            let default_where = if default_bounds.is_empty() {
+                quote!()
+            } else {
+                quote!(where #(#default_bounds),*)
+            };
+            quote! {
+                fn try_from_fields(
+                    fields: impl IntoIterator<Item = #enumname #ty_generics>,
+                ) -> Result<Self, ::field_accessor::FieldAccessError> #default_where {
+                    #(let mut #bindings: Option<#tys> = None;)*
+                    for field in fields {
+                        let (name, duplicate) = match field {
+                            #(#enumname::#variants(value) => (#names, #bindings.replace(value).is_some()),)*
+                            #phantom_arm
+                        };
+                        if duplicate {
+                            return Err(::field_accessor::FieldAccessError::DuplicateField {
+                                field: name.to_string(),
+                            });
+                        }
+                    }
+                    #(#validates)*
+                    let value = Self {
+                        #(#members: match #bindings {
+                            Some(value) => value,
+                            None => return Err(::field_accessor::FieldAccessError::MissingField {
+                                struct_name: stringify!(#ident),
+                                field: #names.to_string(),
+                            }),
+                        },)*
+                        #(#skipped_members: Default::default(),)*
+                    };
+                    #check
+                    Ok(value)
+                }
+            }
+        }
+        _ => quote!(),
+    };
+
+    let assign_arms = setenum_arms(false);
+    let setenum = accessors.guard(quote! {
+        match self {
+            #(#assign_arms)*
+        }
+    });
+    let replace_arms = setenum_arms(true);
+
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn setenum(&mut self, value: #enumname #ty_generics) -> Result<(), ::field_accessor::FieldAccessError> {
+                #setenum
+            }
+
+            #[doc(hidden)]
+            fn __replace_field(&mut self, value: #enumname #ty_generics) -> Result<#enumname #ty_generics, ::field_accessor::FieldAccessError> {
+                match self {
+                    #(#replace_arms)*
+                }
+            }
+
+            fn into_fields(self) -> Vec<#enumname #ty_generics> {
+                match self {
+                    #(#into_fields_arms)*
+                }
+            }
+
+            #try_from_fields
+        }
+    }
+}
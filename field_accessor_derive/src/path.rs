@@ -0,0 +1,313 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::accessors::Accessors;
+use crate::generics::{field_bound, mentions_generic_param, static_bounds, where_with};
+use crate::model::FieldInfo;
+
+/// Implements `field_accessor::FieldPath` and the inherent `*_path` methods.
+pub(crate) fn expand(
+    accessors: &Accessors,
+    generics: &syn::Generics,
+    enumname: &syn::Ident,
+) -> TokenStream {
+    let ident = accessors.ident;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    let bounds = bounds(accessors, generics);
+    let where_clause = where_with(generics, &bounds);
+    // `getenum_path` clones the field it ends at into the field enum.
+    let enum_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .flat_map(|key| {
+            let clone = field_bound(key.ty, generics, &quote!(Clone));
+            let nested = key.nested.then(|| {
+                field_bound(
+                    key.ty,
+                    generics,
+                    &quote!(::field_accessor::__private::FieldPathEnum),
+                )
+            });
+            std::iter::once(clone).chain(nested)
+        })
+        .collect();
+    let enum_where_clause = where_with(generics, &[bounds, enum_bounds].concat());
+
+    // A segment without an index ends at the field or walks into it; one with an index
+    // must be the last and is resolved by `FieldIndex`, if the field type implements it.
+    let resolve =
+        |f: &FieldInfo, leaf: TokenStream, recurse: TokenStream, indexed: Option<TokenStream>| {
+            let unindexed = if f.nested {
+                quote! {
+                    match rest.split_first() {
+                        Some((next, rest)) => return #recurse,
+                        None => Ok(#leaf),
+                    }
+                }
+            } else {
+                quote! {
+                    if rest.is_empty() {
+                        Ok(#leaf)
+                    } else {
+                        Err(::field_accessor::FieldAccessError::NotNested {
+                            field: field_string.to_string(),
+                        })
+                    }
+                }
+            };
+            let indexed = match indexed {
+                Some(indexed) => quote! {
+                    if rest.is_empty() {
+                        #indexed
+                    } else {
+                        Err(::field_accessor::FieldAccessError::NotNested {
+                            field: field_string.to_string(),
+                        })
+                    }
+                },
+                _ => quote! {
+                    Err(::field_accessor::FieldAccessError::NotIndexable {
+                        field: field_string.to_string(),
+                        index: index.to_string(),
+                    })
+                },
+            };
+            quote! {
+                match &segment.index {
+                    None => #unindexed,
+                    Some(index) => #indexed,
+                }
+            }
+        };
+    let path_ref = accessors.match_field(None, false, |f| {
+        let binding = &f.binding;
+        let ty = f.ty;
+        resolve(
+            f,
+            quote!((#binding as &dyn ::core::any::Any, stringify!(#ty))),
+            quote!(::field_accessor::FieldPath::__path_ref(#binding, path, next, rest)),
+            Some(quote!(::field_accessor::__private::Indexed(#binding).index_ref(field_string, index))),
+        )
+    });
+    let path_mut = accessors.match_field(None, true, |f| {
+        let binding = &f.binding;
+        let ty = f.ty;
+        resolve(
+            f,
+            quote!((#binding as &mut dyn ::core::any::Any, stringify!(#ty))),
+            quote!(::field_accessor::FieldPath::__path_mut(#binding, path, next, rest)),
+            Some(quote!(::field_accessor::__private::Indexed(#binding).index_mut(field_string, index))),
+        )
+    });
+    let enum_path = {
+        let turbofish = ty_generics.as_turbofish();
+        quote!(#enumname #turbofish)
+    };
+    let path_enum = accessors.match_field(None, false, |f| {
+        let binding = &f.binding;
+        let variant = &f.variant;
+        resolve(
+            f,
+            quote! {(
+                Box::new(#enum_path::#variant(#binding.clone())) as Box<dyn ::core::any::Any>,
+                stringify!(#enumname),
+            )},
+            quote!(::field_accessor::__private::FieldPathEnum::__path_enum(#binding, path, next, rest)),
+            None,
+        )
+    });
+    // `Resize.0` is parsed as two segments, which are joined back into the field name.
+    let scoped: Vec<_> = accessors
+        .shapes
+        .iter()
+        .filter(|shape| shape.variant.is_some())
+        .flat_map(|shape| shape.fields.iter())
+        .filter(|f| matches!(f.member, syn::Member::Unnamed(_)))
+        .map(|f| &f.name)
+        .collect();
+    let join = if scoped.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            let joined = rest
+                .split_first()
+                .and_then(|(next, rest)| Some((segment.join(path, next, &[#(#scoped),*])?, rest)));
+            let (segment, rest) = match &joined {
+                Some((segment, rest)) => (segment, *rest),
+                None => (segment, rest),
+            };
+        }
+    };
+    // A value set through a path is checked against the validators of the field the path
+    // starts at and the invariant, and put back if it fails them.
+    let restore = quote!(*::field_accessor::FieldPath::get_path_mut(self, path)? = previous;);
+    let validate = if accessors.keys.iter().any(|key| !key.validators.is_empty()) {
+        quote! {
+            let validated = match self.getenum_ref(Self::__path_field(path)) {
+                Ok(field) => Self::__validate(field),
+                Err(_) => Ok(()),
+            };
+            if let Err(error) = validated {
+                #restore
+                return Err(error);
+            }
+        }
+    } else {
+        quote!()
+    };
+    let check = accessors.check_invariant(restore);
+    let in_path = quote! {
+        |source| ::field_accessor::FieldAccessError::InvalidPath {
+            path: path.to_string(),
+            segment: segment.text.to_string(),
+            source: Box::new(source),
+        }
+    };
+
+    quote! {
+        impl #impl_generics ::field_accessor::FieldPath for #ident #ty_generics #where_clause {
+            fn __path_ref(
+                &self,
+                path: &str,
+                segment: &::field_accessor::__private::Segment,
+                rest: &[::field_accessor::__private::Segment],
+            ) -> Result<(&dyn ::core::any::Any, &'static str), ::field_accessor::FieldAccessError> {
+                use ::field_accessor::__private::{IndexFallback as _, IndexRef as _};
+                #join
+                let field_string = segment.name;
+                let found: Result<_, ::field_accessor::FieldAccessError> = #path_ref;
+                found.map_err(#in_path)
+            }
+
+            fn __path_mut(
+                &mut self,
+                path: &str,
+                segment: &::field_accessor::__private::Segment,
+                rest: &[::field_accessor::__private::Segment],
+            ) -> Result<(&mut dyn ::core::any::Any, &'static str), ::field_accessor::FieldAccessError> {
+                use ::field_accessor::__private::{IndexFallback as _, IndexMut as _};
+                #join
+                let field_string = segment.name;
+                let found: Result<_, ::field_accessor::FieldAccessError> = #path_mut;
+                found.map_err(#in_path)
+            }
+
+            fn set_path<__T: ::core::any::Any>(&mut self, path: &str, value: __T) -> Result<(), ::field_accessor::FieldAccessError> {
+                self.__replace_path(path, value).map(drop)
+            }
+
+            fn take_path<__T: ::core::any::Any + Default>(&mut self, path: &str) -> Result<__T, ::field_accessor::FieldAccessError> {
+                self.__replace_path(path, Default::default())
+            }
+        }
+
+        impl #impl_generics ::field_accessor::__private::FieldPathEnum for #ident #ty_generics #enum_where_clause {
+            fn __path_enum(
+                &self,
+                path: &str,
+                segment: &::field_accessor::__private::Segment,
+                rest: &[::field_accessor::__private::Segment],
+            ) -> Result<(Box<dyn ::core::any::Any>, &'static str), ::field_accessor::FieldAccessError> {
+                #join
+                let field_string = segment.name;
+                let found: Result<_, ::field_accessor::FieldAccessError> = #path_enum;
+                found.map_err(#in_path)
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn get_path<__T: ::core::any::Any>(&self, path: &str) -> Result<&__T, ::field_accessor::FieldAccessError> {
+                ::field_accessor::FieldPath::get_path(self, path)
+            }
+
+            fn get_path_mut<__T: ::core::any::Any>(&mut self, path: &str) -> Result<&mut __T, ::field_accessor::FieldAccessError> {
+                ::field_accessor::FieldPath::get_path_mut(self, path)
+            }
+
+            fn set_path<__T: ::core::any::Any>(&mut self, path: &str, value: __T) -> Result<(), ::field_accessor::FieldAccessError> {
+                ::field_accessor::FieldPath::set_path(self, path, value)
+            }
+
+            fn take_path<__T: ::core::any::Any + Default>(&mut self, path: &str) -> Result<__T, ::field_accessor::FieldAccessError> {
+                ::field_accessor::FieldPath::take_path(self, path)
+            }
+
+            /// Puts `value` at `path` and returns the value that was there, for `set_path`,
+            /// `take_path` and the accessors given a path.
+            #[doc(hidden)]
+            fn __replace_path<__T: ::core::any::Any>(&mut self, path: &str, value: __T) -> Result<__T, ::field_accessor::FieldAccessError> {
+                let field = ::field_accessor::FieldPath::get_path_mut(self, path)?;
+                let previous = ::core::mem::replace(field, value);
+                #validate
+                #check
+                Ok(previous)
+            }
+
+            fn getenum_path<__E: ::core::any::Any>(&self, path: &str) -> Result<__E, ::field_accessor::FieldAccessError>
+            where
+                for<'__bound> Self: ::field_accessor::__private::FieldPathEnum,
+            {
+                ::field_accessor::FieldPath::getenum_path(self, path)
+            }
+        }
+    }
+}
+
+/// The bounds the `*_path` methods need. Fields and the field enum are handed out as
+/// `dyn Any`, so the generics must be `'static`.
+pub(crate) fn bounds(accessors: &Accessors, generics: &syn::Generics) -> Vec<TokenStream> {
+    let mut bounds = static_bounds(generics);
+    for key in accessors.keys.iter() {
+        let ty = key.ty;
+        if mentions_generic_param(ty, generics) {
+            if key.nested {
+                bounds.push(quote!(#ty: ::field_accessor::FieldPath));
+            }
+            // Without the bound, indexing a generic field would report `NotIndexable`.
+            if is_indexable(ty) {
+                bounds.push(quote!(#ty: ::field_accessor::__private::FieldIndex));
+            }
+        }
+    }
+    bounds
+}
+
+/// Whether `ty` is spelled as one of the sequences or maps that paths can index into.
+fn is_indexable(ty: &syn::Type) -> bool {
+    element_type(ty).is_some()
+}
+
+/// The type of the values that indexing `ty` with a path reaches, if `ty` is spelled as
+/// one of the sequences or maps that paths can index into.
+pub(crate) fn element_type(ty: &syn::Type) -> Option<&syn::Type> {
+    match ty {
+        syn::Type::Array(array) => Some(&array.elem),
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Slice(slice) => Some(&slice.elem),
+            _ => None,
+        },
+        syn::Type::Group(group) => element_type(&group.elem),
+        syn::Type::Paren(paren) => element_type(&paren.elem),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let last = path.path.segments.last()?;
+            let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+                return None;
+            };
+            let mut tys = args.args.iter().filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            });
+            match last.ident.to_string().as_str() {
+                "Vec" => tys.next(),
+                "HashMap" | "BTreeMap" => tys.nth(1),
+                "Box" => match tys.next()? {
+                    syn::Type::Slice(slice) => Some(&slice.elem),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
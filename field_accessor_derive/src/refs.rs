@@ -0,0 +1,86 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::phantom_variant;
+use crate::impls::FieldEnumDef;
+
+/// Generates `(StructName)FieldRef` and `(StructName)FieldMut`, which borrow a field
+/// instead of cloning it, and the `getenum_ref` and `getenum_mut` methods returning them.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let ident = accessors.ident;
+    let vis = accessors.vis;
+    let refname = format_ident!("{}FieldRef", ident);
+    let mutname = format_ident!("{}FieldMut", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut ref_generics = generics.clone();
+    ref_generics.params.insert(0, syn::parse_quote!('__fa));
+    let (_, ref_ty_generics, _) = ref_generics.split_for_impl();
+
+    let variants: Vec<_> = accessors.keys.iter().map(|f| &f.variant).collect();
+    let tys: Vec<_> = accessors.keys.iter().map(|f| f.ty).collect();
+    let ref_tys: Vec<syn::Type> = tys.iter().map(|ty| syn::parse_quote!(&'__fa #ty)).collect();
+    let phantom = phantom_variant(&ref_generics, &ref_tys.iter().collect::<Vec<_>>());
+
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+    let enum_impls: Vec<_> = [&refname, &mutname]
+        .into_iter()
+        .flat_map(|name| {
+            let def = FieldEnumDef {
+                name,
+                generics: &ref_generics,
+                struct_generics: generics,
+                variants: &variants,
+                tys: &tys,
+                has_phantom: !phantom.is_empty(),
+            };
+            [def.debug(), def.partial_eq()]
+        })
+        .collect();
+
+    let getenum_ref = accessors.match_field(None, false, |f| {
+        let binding = &f.binding;
+        let variant = &f.variant;
+        quote!(Ok(#refname::#variant(#binding)))
+    });
+    let getenum_mut = accessors.match_field(None, true, |f| {
+        let binding = &f.binding;
+        let variant = &f.variant;
+        quote!(Ok(#mutname::#variant(#binding)))
+    });
+
+    quote! {
+        #[allow(non_camel_case_types)]
+        #vis enum #refname #ref_generics #where_clause {
+            #(#variants(&'__fa #tys),)*
+            #phantom
+        }
+
+        impl #ref_impl_generics Clone for #refname #ref_ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #ref_impl_generics Copy for #refname #ref_ty_generics #where_clause {}
+
+        #[allow(non_camel_case_types)]
+        #vis enum #mutname #ref_generics #where_clause {
+            #(#variants(&'__fa mut #tys),)*
+            #phantom
+        }
+
+        #(#enum_impls)*
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn getenum_ref<'__fa>(&'__fa self, field_string: &str) -> Result<#refname #ref_ty_generics, ::field_accessor::FieldAccessError> {
+                #getenum_ref
+            }
+
+            fn getenum_mut<'__fa>(&'__fa mut self, field_string: &str) -> Result<#mutname #ref_ty_generics, ::field_accessor::FieldAccessError> {
+                #getenum_mut
+            }
+        }
+    }
+}
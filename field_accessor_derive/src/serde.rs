@@ -0,0 +1,307 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::field_bound;
+
+/// Set when `field_accessor` is built with its `serde` feature, which enables ours.
+const ENABLED: bool = cfg!(feature = "serde");
+
+/// The serde derives for `(StructName)FieldEnum`. As with its other impls, the bounds are
+/// on the field types, so a struct with a field serde cannot handle still compiles.
+pub(crate) fn field_enum_attrs(tys: &[&syn::Type], generics: &syn::Generics) -> TokenStream {
+    if !ENABLED {
+        return quote!();
+    }
+    let bounds = |bound: TokenStream| {
+        tys.iter()
+            .map(|ty| field_bound(ty, generics, &bound).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let serialize = bounds(quote!(::field_accessor::__private::serde::Serialize));
+    let deserialize = bounds(quote!(::field_accessor::__private::serde::Deserialize<'de>));
+    quote! {
+        #[derive(
+            ::field_accessor::__private::serde::Serialize,
+            ::field_accessor::__private::serde::Deserialize
+        )]
+        #[serde(
+            crate = "::field_accessor::__private::serde",
+            bound(serialize = #serialize, deserialize = #deserialize)
+        )]
+    }
+}
+
+/// The serde attributes for `(StructName)Op`, which is written like
+/// `{"op": "swap", "a": "x", "b": "y"}`, and for its field holding a
+/// `(StructName)FieldEnum`.
+pub(crate) fn op_attrs(tys: &[&syn::Type], generics: &syn::Generics) -> (TokenStream, TokenStream) {
+    if !ENABLED {
+        return (quote!(), quote!());
+    }
+    let enum_attrs = field_enum_attrs(tys, generics);
+    // The field enum may borrow from the input like the struct's own fields.
+    let value_attrs = if generics.lifetimes().next().is_some() {
+        quote!(#[serde(borrow)])
+    } else {
+        quote!()
+    };
+    (
+        quote! {
+            #enum_attrs
+            #[serde(tag = "op", rename_all = "lowercase")]
+        },
+        value_attrs,
+    )
+}
+
+/// The serde derives for `(StructName)Fields`.
+pub(crate) fn fields_attrs() -> TokenStream {
+    if !ENABLED {
+        return quote!();
+    }
+    quote! {
+        #[derive(
+            ::field_accessor::__private::serde::Serialize,
+            ::field_accessor::__private::serde::Deserialize
+        )]
+        #[serde(crate = "::field_accessor::__private::serde")]
+    }
+}
+
+/// Names each variant after its field, so that both enums use the field names on the wire.
+pub(crate) fn variant_attrs(names: &[&String]) -> Vec<TokenStream> {
+    names
+        .iter()
+        .map(|name| {
+            if ENABLED {
+                quote!(#[serde(rename = #name)])
+            } else {
+                quote!()
+            }
+        })
+        .collect()
+}
+
+/// Keeps serde away from the uninhabited `__Phantom` variant, if there is one.
+pub(crate) fn phantom_attrs(phantom: &TokenStream) -> TokenStream {
+    if !ENABLED || phantom.is_empty() {
+        return quote!();
+    }
+    quote!(#[serde(skip)])
+}
+
+/// Generates `get_json` and `set_json`, which convert a field to and from a
+/// `serde_json::Value`, `to_json_map`, and `apply_json_patch` and `diff_json_patch`,
+/// which work on the JSON form of the fields.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    if !ENABLED {
+        return quote!();
+    }
+    let ident = accessors.ident;
+    let enumname = format_ident!("{}FieldEnum", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let bounds = |bound: TokenStream| {
+        let bounds = accessors
+            .keys
+            .iter()
+            .map(|f| field_bound(f.ty, generics, &bound));
+        quote!(where #(#bounds),*)
+    };
+    let serialize = quote!(::field_accessor::__private::serde::Serialize);
+    let deserialize = quote!(::field_accessor::__private::serde::de::DeserializeOwned);
+    let serialize_where = bounds(serialize.clone());
+    let deserialize_where = bounds(deserialize.clone());
+    // Read-only fields are never written back from a patch, so they need not deserialize.
+    let patch_bounds = accessors.keys.iter().flat_map(|f| {
+        let serialize = field_bound(f.ty, generics, &serialize);
+        let deserialize = (!f.readonly).then(|| field_bound(f.ty, generics, &deserialize));
+        std::iter::once(serialize).chain(deserialize)
+    });
+    let patch_where = quote!(where #(#patch_bounds),*);
+
+    let get_json = accessors.match_field(None, false, |f| {
+        let binding = &f.binding;
+        quote! {
+            ::field_accessor::__private::serde_json::to_value(#binding).map_err(|error| {
+                ::field_accessor::FieldAccessError::SerializeFailed {
+                    field: field_string.to_string(),
+                    message: error.to_string(),
+                }
+            })
+        }
+    });
+    let set_json = accessors.guard(accessors.match_field(None, true, |f| {
+        let ty = f.ty;
+        let assign = accessors.assign(f, quote!(value));
+        quote! {
+            match ::field_accessor::__private::serde_json::from_value::<#ty>(value) {
+                Ok(value) => #assign
+                Err(error) => Err(::field_accessor::FieldAccessError::DeserializeFailed {
+                    field: field_string.to_string(),
+                    expected: stringify!(#ty),
+                    message: error.to_string(),
+                }),
+            }
+        }
+    }));
+
+    let to_json_map_arms = accessors.shapes.iter().map(|shape| {
+        let pattern = shape.pattern(|_| true);
+        let names = shape.names();
+        let bindings = shape.fields.iter().map(|f| &f.binding);
+        quote! {
+            #pattern => Ok(::field_accessor::__private::serde_json::Map::from_iter([#(
+                (
+                    #names.to_string(),
+                    ::field_accessor::__private::serde_json::to_value(#bindings).map_err(|error| {
+                        ::field_accessor::FieldAccessError::SerializeFailed {
+                            field: #names.to_string(),
+                            message: error.to_string(),
+                        }
+                    })?,
+                )
+            ),*])),
+        }
+    });
+
+    // The patch is applied to the JSON form first, and the changed fields are only
+    // assigned once all of them have been deserialized, so a failure changes nothing.
+    let readonly_names = accessors
+        .keys
+        .iter()
+        .filter(|f| f.readonly)
+        .map(|f| &f.name);
+    let apply_arms = accessors.shapes.iter().map(|shape| {
+        let writable: Vec<_> = shape.fields.iter().filter(|f| !f.readonly).collect();
+        let pattern = shape.pattern(|f| !f.readonly);
+        let names = writable.iter().map(|f| &f.name);
+        let tys = writable.iter().map(|f| f.ty);
+        let bindings = writable.iter().map(|f| &f.binding);
+        let changed: Vec<_> = writable
+            .iter()
+            .map(|f| format_ident!("{}_changed", f.binding))
+            .collect();
+        let validates = writable
+            .iter()
+            .map(|f| accessors.validate(f, quote!(value)));
+        // With an invariant, the previous values are kept to undo the whole patch.
+        let assign = if accessors.invariant.is_some() {
+            let variants = writable.iter().map(|f| &f.variant);
+            quote! {
+                let mut previous = vec![];
+                #(
+                    if let Some(value) = #changed {
+                        previous.push(#enumname::#variants(std::mem::replace(#bindings, value)));
+                    }
+                )*
+                Ok(previous)
+            }
+        } else {
+            quote! {
+                #(
+                    if let Some(value) = #changed {
+                        *#bindings = value;
+                    }
+                )*
+                Ok(())
+            }
+        };
+        quote! {
+            #pattern => {
+                #(
+                    let #changed = match document.take_changed(&original, #names) {
+                        Some(value) => Some(
+                            ::field_accessor::__private::serde_json::from_value::<#tys>(value).map_err(|error| {
+                                ::field_accessor::FieldAccessError::DeserializeFailed {
+                                    field: #names.to_string(),
+                                    expected: stringify!(#tys),
+                                    message: error.to_string(),
+                                }
+                            })?,
+                        ),
+                        None => None,
+                    };
+                )*
+                #(
+                    if let Some(value) = &#changed {
+                        #validates
+                    }
+                )*
+                #assign
+            }
+        }
+    });
+
+    // The fields of another variant could only be added and the old ones removed, which
+    // `apply_json_patch` does not allow.
+    let same_variant = if accessors.shapes.iter().any(|shape| shape.variant.is_some()) {
+        quote! {
+            if self.variant_name() != other.variant_name() {
+                return Err(::field_accessor::FieldAccessError::VariantMismatch {
+                    variant: self.variant_name(),
+                    found: other.variant_name(),
+                });
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    let check = accessors.check_invariant(quote! {
+        for value in previous {
+            self.__replace_field(value)?;
+        }
+    });
+    let apply = if accessors.invariant.is_some() {
+        quote! {
+            let previous = match self {
+                #(#apply_arms)*
+            }?;
+            #check
+            Ok(())
+        }
+    } else {
+        quote! {
+            match self {
+                #(#apply_arms)*
+            }
+        }
+    };
+
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn to_json_map(&self) -> Result<::field_accessor::__private::serde_json::Map<String, ::field_accessor::__private::serde_json::Value>, ::field_accessor::FieldAccessError> #serialize_where {
+                match self {
+                    #(#to_json_map_arms)*
+                }
+            }
+
+            fn apply_json_patch(&mut self, patch: &[::field_accessor::PatchOperation]) -> Result<(), ::field_accessor::FieldAccessError> #patch_where {
+                let original = self.to_json_map()?;
+                let mut document = ::field_accessor::__private::JsonFields {
+                    struct_name: stringify!(#ident),
+                    readonly: &[#(#readonly_names),*],
+                    fields: original.clone(),
+                };
+                document.apply(patch)?;
+                #apply
+            }
+
+            fn diff_json_patch(&self, other: &Self) -> Result<Vec<::field_accessor::PatchOperation>, ::field_accessor::FieldAccessError> #serialize_where {
+                #same_variant
+                Ok(::field_accessor::__private::diff(&self.to_json_map()?, &other.to_json_map()?))
+            }
+
+            fn get_json(&self, field_string: &str) -> Result<::field_accessor::__private::serde_json::Value, ::field_accessor::FieldAccessError> #serialize_where {
+                #get_json
+            }
+
+            fn set_json(&mut self, field_string: &str, value: ::field_accessor::__private::serde_json::Value) -> Result<(), ::field_accessor::FieldAccessError> #deserialize_where {
+                #set_json
+            }
+        }
+    }
+}
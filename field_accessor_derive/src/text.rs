@@ -0,0 +1,136 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::generics::field_bound;
+use crate::model::FieldInfo;
+
+/// Generates `set_from_str`, which parses text into a field with its `FromStr` impl, and
+/// `get_string` and `to_string_map`, which format fields with `Display` or `Debug`.
+/// The hidden `__parse_field` parses without assigning, for transactions.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let ident = accessors.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let parse_where = parse_where(accessors, generics);
+    let enumname = format_ident!("{}FieldEnum", ident);
+    // `parse(f, on_ok)` evaluates `on_ok` with the parsed `value`.
+    let parse = |f: &FieldInfo, on_ok: TokenStream| {
+        let ty = f.ty;
+        if f.no_parse {
+            return quote! {
+                Err(::field_accessor::FieldAccessError::NotParsable {
+                    field: field_string.to_string(),
+                })
+            };
+        }
+        quote! {
+            match <#ty as ::core::str::FromStr>::from_str(value) {
+                Ok(value) => #on_ok
+                Err(error) => Err(::field_accessor::FieldAccessError::ParseFailed {
+                    field: field_string.to_string(),
+                    expected: stringify!(#ty),
+                    message: error.to_string(),
+                }),
+            }
+        }
+    };
+    let set_from_str = accessors
+        .guard(accessors.match_field(None, true, |f| parse(f, accessors.assign(f, quote!(value)))));
+    let parse_field = accessors.match_field(None, true, |f| {
+        let variant = &f.variant;
+        parse(f, quote!({ Ok(#enumname::#variant(value)) }))
+    });
+
+    // As with parsing, `get_string` is only available once every field can be formatted.
+    let format_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .map(|f| {
+            let format_trait = if f.debug_fmt {
+                quote!(::core::fmt::Debug)
+            } else {
+                quote!(::core::fmt::Display)
+            };
+            field_bound(f.ty, generics, &format_trait)
+        })
+        .collect();
+    let format_where = if format_bounds.is_empty() {
+        quote!()
+    } else {
+        quote!(where #(#format_bounds),*)
+    };
+    let format = |f: &FieldInfo| {
+        let binding = &f.binding;
+        if f.debug_fmt {
+            quote!(format!("{:?}", #binding))
+        } else {
+            quote!(#binding.to_string())
+        }
+    };
+    let get_string = accessors.match_field(None, false, |f| {
+        let formatted = format(f);
+        quote!(Ok(#formatted))
+    });
+    let to_string_map_arms = accessors.shapes.iter().map(|shape| {
+        let pattern = shape.pattern(|_| true);
+        let names = shape.names();
+        let formatted = shape.fields.iter().map(format);
+        quote! {
+            #pattern => ::std::collections::BTreeMap::from([
+                #((#names.to_string(), #formatted)),*
+            ]),
+        }
+    });
+
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            fn get_string(&self, field_string: &str) -> Result<String, ::field_accessor::FieldAccessError> #format_where {
+                #get_string
+            }
+
+            fn to_string_map(&self) -> ::std::collections::BTreeMap<String, String> #format_where {
+                match self {
+                    #(#to_string_map_arms)*
+                }
+            }
+
+            fn set_from_str(&mut self, field_string: &str, value: &str) -> Result<(), ::field_accessor::FieldAccessError> #parse_where {
+                #set_from_str
+            }
+
+            /// Parses `value` as the named field without assigning it.
+            #[doc(hidden)]
+            fn __parse_field(&self, field_string: &str, value: &str) -> Result<#enumname #ty_generics, ::field_accessor::FieldAccessError> #parse_where {
+                #parse_field
+            }
+        }
+    }
+}
+
+/// The bounds for parsing every field not marked `no_parse`. A struct with fields that
+/// cannot be parsed still compiles, but `set_from_str` is only available once they are
+/// marked `no_parse`.
+pub(crate) fn parse_where(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let parse_bounds: Vec<_> = accessors
+        .keys
+        .iter()
+        .filter(|f| !f.no_parse)
+        .flat_map(|f| {
+            let ty = f.ty;
+            [
+                field_bound(ty, generics, &quote!(::core::str::FromStr)),
+                field_bound(
+                    &syn::parse_quote!(<#ty as ::core::str::FromStr>::Err),
+                    generics,
+                    &quote!(::core::fmt::Display),
+                ),
+            ]
+        })
+        .collect();
+    if parse_bounds.is_empty() {
+        quote!()
+    } else {
+        quote!(where #(#parse_bounds),*)
+    }
+}
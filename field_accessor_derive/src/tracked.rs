@@ -0,0 +1,132 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+
+/// Generates `(StructName)Tracked` for `#[field_accessor(track_changes)]`. The wrapper
+/// only hands out the value mutably through the accessors, so it can flag every field
+/// they modify.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let ident = accessors.ident;
+    let vis = accessors.vis;
+    let tracked = format_ident!("{}Tracked", ident);
+    let gettersetter = format_ident!("{}GetterSetter", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let names: Vec<_> = accessors.keys.iter().map(|f| &f.name).collect();
+    let field_count = names.len();
+
+    quote! {
+        /// Wraps a value and records which of its fields were modified.
+        #vis struct #tracked #generics #where_clause {
+            value: #ident #ty_generics,
+            dirty: [bool; #field_count],
+        }
+
+        impl #impl_generics #tracked #ty_generics #where_clause {
+            const FIELD_NAMES: [&'static str; #field_count] = [#(#names),*];
+
+            fn new(value: #ident #ty_generics) -> Self {
+                Self {
+                    value,
+                    dirty: [false; #field_count],
+                }
+            }
+
+            fn into_inner(self) -> #ident #ty_generics {
+                self.value
+            }
+
+            /// The modified fields, in declaration order.
+            fn dirty_fields(&self) -> Vec<&'static str> {
+                Self::FIELD_NAMES
+                    .into_iter()
+                    .zip(self.dirty)
+                    .filter(|(_, dirty)| *dirty)
+                    .map(|(name, _)| name)
+                    .collect()
+            }
+
+            fn is_dirty(&self, field_string: &str) -> bool {
+                Self::position(field_string).is_some_and(|i| self.dirty[i])
+            }
+
+            fn clear_dirty(&mut self) {
+                self.dirty = [false; #field_count];
+            }
+
+            /// Flags a field as modified, e.g. after changing it through a path. Given a
+            /// path, flags the field it starts at.
+            fn mark_dirty(&mut self, field_string: &str) {
+                if let Some(i) = Self::position(field_string) {
+                    self.dirty[i] = true;
+                }
+            }
+
+            fn position(field_string: &str) -> Option<usize> {
+                let field_string = <#ident #ty_generics>::__path_field(field_string);
+                Self::FIELD_NAMES.iter().position(|name| *name == field_string)
+            }
+
+            fn get<__T, __M>(&self, field_string: &str) -> Result<&__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.value.get(field_string)
+            }
+
+            /// Flags the field as modified, whether or not the reference is written to.
+            fn get_mut<__T, __M>(&mut self, field_string: &str) -> Result<&mut __T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                let field = self.value.get_mut(field_string)?;
+                if let Some(i) = Self::position(field_string) {
+                    self.dirty[i] = true;
+                }
+                Ok(field)
+            }
+
+            fn set<__T, __M>(&mut self, field_string: &str, value: __T) -> Result<(), ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                self.value.set(field_string, value)?;
+                self.mark_dirty(field_string);
+                Ok(())
+            }
+
+            fn replace<__T, __M>(&mut self, field_string: &str, src: __T) -> Result<__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                let previous = self.value.replace(field_string, src)?;
+                self.mark_dirty(field_string);
+                Ok(previous)
+            }
+
+            fn take<__T: Default, __M>(&mut self, field_string: &str) -> Result<__T, ::field_accessor::FieldAccessError>
+            where
+                #ident #ty_generics: #gettersetter<__T, __M>,
+            {
+                let previous = self.value.take(field_string)?;
+                self.mark_dirty(field_string);
+                Ok(previous)
+            }
+
+            fn swap(&mut self, field_string: &str, field_string_y: &str) -> Result<(), ::field_accessor::FieldAccessError> {
+                self.value.swap(field_string, field_string_y)?;
+                self.mark_dirty(field_string);
+                self.mark_dirty(field_string_y);
+                Ok(())
+            }
+        }
+
+        impl #impl_generics ::core::ops::Deref for #tracked #ty_generics #where_clause {
+            type Target = #ident #ty_generics;
+
+            fn deref(&self) -> &Self::Target {
+                &self.value
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::text::parse_where;
+
+/// Generates `begin` and `(StructName)Transaction`, which collects changes by field name
+/// and applies them on `commit` only if all of them pass their checks.
+pub(crate) fn expand(
+    accessors: &Accessors,
+    generics: &syn::Generics,
+    enumname: &syn::Ident,
+) -> TokenStream {
+    let ident = accessors.ident;
+    let vis = accessors.vis;
+    let transaction = format_ident!("{}Transaction", ident);
+    let refname = format_ident!("{}FieldRef", ident);
+    let gettersetter = format_ident!("{}GetterSetter", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut tx_generics = generics.clone();
+    tx_generics.params.insert(0, syn::parse_quote!('__fa));
+    let (tx_impl_generics, tx_ty_generics, _) = tx_generics.split_for_impl();
+    let parse_where = parse_where(accessors, generics);
+
+    let names = accessors.keys.iter().map(|f| &f.name);
+    let validated: Vec<_> = accessors
+        .keys
+        .iter()
+        .filter(|f| !f.validators.is_empty())
+        .map(|f| &f.variant)
+        .collect();
+    let validate = if validated.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            for field in staged.iter() {
+                #[allow(unreachable_patterns)]
+                match field {
+                    #(#enumname::#validated(value) => #ident::__validate(#refname::#validated(value))?,)*
+                    _ => {}
+                }
+            }
+        }
+    };
+    let check_invariant = accessors.invariant.map(|_| {
+        quote! {
+            if let Err(error) = value.__check_invariant() {
+                restore(value, previous);
+                return Err(error);
+            }
+        }
+    });
+
+    quote! {
+        /// Changes to a value that are applied together by `commit`. Dropping it discards them.
+        #vis struct #transaction #tx_generics #where_clause {
+            value: &'__fa mut #ident #ty_generics,
+            staged: Vec<#enumname #ty_generics>,
+            error: Option<::field_accessor::FieldAccessError>,
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Starts collecting changes to apply all at once.
+            fn begin<'__fa>(&'__fa mut self) -> #transaction #tx_ty_generics {
+                #transaction {
+                    value: self,
+                    staged: vec![],
+                    error: None,
+                }
+            }
+        }
+
+        impl #tx_impl_generics #transaction #tx_ty_generics #where_clause {
+            fn set<__T, __M>(&mut self, field_string: &str, value: __T) -> &mut Self
+            where
+                #ident #ty_generics: #gettersetter<__T, __M, __FieldEnum = #enumname #ty_generics>,
+            {
+                let field = Self::field_name(field_string)
+                    .and_then(|()| self.value.__field_enum(field_string, value));
+                self.stage(field)
+            }
+
+            fn set_from_str(&mut self, field_string: &str, value: &str) -> &mut Self #parse_where {
+                let field = Self::field_name(field_string)
+                    .and_then(|()| self.value.__parse_field(field_string, value));
+                self.stage(field)
+            }
+
+            /// Changes are staged as whole fields, so a path is rejected instead of being
+            /// looked up as a field name.
+            fn field_name(field_string: &str) -> Result<(), ::field_accessor::FieldAccessError> {
+                if field_string.contains('[')
+                    || (field_string.contains('.') && ![#(#names),*].contains(&field_string))
+                {
+                    return Err(::field_accessor::FieldAccessError::PathNotSupported {
+                        path: field_string.to_string(),
+                    });
+                }
+                Ok(())
+            }
+
+            fn setenum(&mut self, value: #enumname #ty_generics) -> &mut Self {
+                self.stage(Ok(value))
+            }
+
+            /// Keeps the first error, which `commit` returns instead of applying anything.
+            fn stage(&mut self, field: Result<#enumname #ty_generics, ::field_accessor::FieldAccessError>) -> &mut Self {
+                match field {
+                    Ok(field) => self.staged.push(field),
+                    Err(error) => {
+                        self.error.get_or_insert(error);
+                    }
+                }
+                self
+            }
+
+            /// Applies the staged changes in order. If a change is rejected, the changes
+            /// already applied are undone, so the value is left as it was.
+            fn commit(self) -> Result<(), ::field_accessor::FieldAccessError> {
+                let Self { value, staged, error } = self;
+                if let Some(error) = error {
+                    return Err(error);
+                }
+                #validate
+                let restore = |value: &mut #ident #ty_generics, previous: Vec<#enumname #ty_generics>| {
+                    for field in previous.into_iter().rev() {
+                        let _ = value.__replace_field(field);
+                    }
+                };
+                let mut previous = vec![];
+                for field in staged {
+                    match value.__replace_field(field) {
+                        Ok(field) => previous.push(field),
+                        Err(error) => {
+                            restore(value, previous);
+                            return Err(error);
+                        }
+                    }
+                }
+                #check_invariant
+                Ok(())
+            }
+        }
+    }
+}
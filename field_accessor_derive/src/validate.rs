@@ -0,0 +1,154 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::accessors::Accessors;
+use crate::attr::Validator;
+
+/// Generates `validate_all`, the hidden `__validate`, which runs the validators of the
+/// field a `(StructName)FieldRef` holds, and the hidden `__check_invariant`. Every
+/// generated method that assigns a new value to a field calls `__validate` first and
+/// `__check_invariant` afterwards.
+pub(crate) fn expand(accessors: &Accessors, generics: &syn::Generics) -> TokenStream {
+    let ident = accessors.ident;
+    let refname = format_ident!("{}FieldRef", ident);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut ref_generics = generics.clone();
+    ref_generics.params.insert(0, syn::parse_quote!('__fa));
+    let (_, ref_ty_generics, _) = ref_generics.split_for_impl();
+
+    let validated: Vec<_> = accessors
+        .keys
+        .iter()
+        .filter(|f| !f.validators.is_empty())
+        .collect();
+    let arms = validated.iter().map(|f| {
+        let variant = &f.variant;
+        let name = &f.name;
+        let checks = f.validators.iter().map(check);
+        quote! {
+            #refname::#variant(value) => {
+                let fail = |reason: String| {
+                    Err(::field_accessor::FieldAccessError::ValidationFailed {
+                        field: #name.to_string(),
+                        reason,
+                    })
+                };
+                #(#checks)*
+                Ok(())
+            }
+        }
+    });
+    let (validate_fn, validate_fields) = if validated.is_empty() {
+        (quote!(), quote!())
+    } else {
+        let names = validated.iter().map(|f| &f.name);
+        (
+            quote! {
+                #[doc(hidden)]
+                fn __validate<'__fa>(field: #refname #ref_ty_generics) -> Result<(), ::field_accessor::FieldAccessError> {
+                    #[allow(unreachable_patterns)]
+                    match field {
+                        #(#arms)*
+                        _ => Ok(()),
+                    }
+                }
+            },
+            // Fields outside the active variant of an enum have nothing to check.
+            quote! {
+                for field in [#(#names),*] {
+                    if let Ok(value) = self.getenum_ref(field) {
+                        Self::__validate(value)?;
+                    }
+                }
+            },
+        )
+    };
+    let (invariant_fn, validate_invariant) = match accessors.invariant {
+        Some(invariant) => (
+            quote! {
+                #[doc(hidden)]
+                fn __check_invariant(&self) -> Result<(), ::field_accessor::FieldAccessError> {
+                    #invariant(self).map_err(|error| ::field_accessor::FieldAccessError::InvariantViolated {
+                        reason: error.to_string(),
+                    })
+                }
+            },
+            quote!(self.__check_invariant()?;),
+        ),
+        None => (quote!(), quote!()),
+    };
+
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #validate_fn
+
+            #invariant_fn
+
+            /// Runs the validators of every field, then the invariant.
+            fn validate_all(&self) -> Result<(), ::field_accessor::FieldAccessError> {
+                #validate_fields
+                #validate_invariant
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns `fail(reason)` if `value` does not pass `validator`.
+fn check(validator: &Validator) -> TokenStream {
+    match validator {
+        Validator::Custom(path) => quote! {
+            if let Err(error) = #path(value) {
+                return fail(error.to_string());
+            }
+        },
+        Validator::Range { min, max } => {
+            let min = min.as_ref().map(|bound| {
+                let (expr, text) = (&bound.expr, &bound.text);
+                quote! {
+                    if *value < #expr {
+                        return fail(concat!("must be at least ", #text).to_string());
+                    }
+                }
+            });
+            let max = max.as_ref().map(|bound| {
+                let (expr, text) = (&bound.expr, &bound.text);
+                quote! {
+                    if *value > #expr {
+                        return fail(concat!("must be at most ", #text).to_string());
+                    }
+                }
+            });
+            quote!(#min #max)
+        }
+        Validator::MinLen(len) => {
+            let len = Literal::usize_unsuffixed(*len);
+            quote! {
+                if value.len() < #len {
+                    return fail(concat!("must have a length of at least ", #len).to_string());
+                }
+            }
+        }
+        Validator::MaxLen(len) => {
+            let len = Literal::usize_unsuffixed(*len);
+            quote! {
+                if value.len() > #len {
+                    return fail(concat!("must have a length of at most ", #len).to_string());
+                }
+            }
+        }
+        // The pattern was checked when the derive ran, so compiling it cannot fail.
+        Validator::Regex(pattern) => quote! {
+            {
+                static REGEX: ::std::sync::OnceLock<::field_accessor::__private::regex::Regex> =
+                    ::std::sync::OnceLock::new();
+                let regex = REGEX.get_or_init(|| {
+                    ::field_accessor::__private::regex::Regex::new(#pattern).unwrap()
+                });
+                if !regex.is_match(::core::convert::AsRef::<str>::as_ref(value)) {
+                    return fail(concat!("must match the pattern `", #pattern, "`").to_string());
+                }
+            }
+        },
+    }
+}
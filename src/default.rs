@@ -0,0 +1,34 @@
+use std::marker::PhantomData;
+
+use crate::FieldAccessError;
+
+/// Lets the generated code produce the default value of a field type if it implements
+/// `Default` and report `NoDefault` otherwise.
+///
+/// As with [`Indexed`](crate::index::Indexed), `Defaulted::<T>(PhantomData).default_value(..)`
+/// resolves to [`DefaultValue`], which takes the wrapper by value, when `T: Default`.
+/// Otherwise method lookup goes on to [`DefaultFallback`], which takes it by reference.
+#[doc(hidden)]
+pub struct Defaulted<T>(pub PhantomData<T>);
+
+#[doc(hidden)]
+pub trait DefaultValue<T> {
+    fn default_value(self, field: &str) -> Result<T, FieldAccessError>;
+}
+
+impl<T: Default> DefaultValue<T> for Defaulted<T> {
+    fn default_value(self, _field: &str) -> Result<T, FieldAccessError> {
+        Ok(T::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait DefaultFallback<T> {
+    fn default_value(&self, field: &str) -> Result<T, FieldAccessError> {
+        Err(FieldAccessError::NoDefault {
+            field: field.to_string(),
+        })
+    }
+}
+
+impl<T> DefaultFallback<T> for Defaulted<T> {}
@@ -0,0 +1,34 @@
+use std::any::Any;
+
+/// A field whose value differs between two instances, as returned by the generated
+/// `diff` and `diff_nested` methods.
+///
+/// `diff` borrows the values as the struct's `(StructName)FieldRef`; `diff_nested`
+/// reports fields of nested structs too, so it borrows them as `dyn Any`. A value is
+/// `None` if the field belongs to an enum variant that the value on that side is not.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange<V> {
+    /// The field name, or for `diff_nested` its dotted path such as `"data.some_value"`.
+    pub field: String,
+    pub old: Option<V>,
+    pub new: Option<V>,
+}
+
+impl<'a> FieldChange<&'a dyn Any> {
+    /// The old and new values, if both sides have the field and it has type `T`.
+    pub fn downcast<T: Any>(&self) -> Option<(&'a T, &'a T)> {
+        Some((self.old?.downcast_ref()?, self.new?.downcast_ref()?))
+    }
+}
+
+/// The recursion behind `diff_nested`, implemented only if every field can be compared
+/// and every nested field implements it too.
+#[doc(hidden)]
+pub trait FieldDiff {
+    fn __diff_fields<'a>(
+        &'a self,
+        other: &'a Self,
+        prefix: &str,
+        changes: &mut Vec<FieldChange<&'a dyn Any>>,
+    );
+}
@@ -0,0 +1,54 @@
+use std::any::Any;
+
+use crate::FieldAccessError;
+
+/// Field access through `dyn Any`, for collections of different derived types.
+///
+/// `#[derive(FieldAccessor)]` implements this trait for types whose generic parameters
+/// are `'static`. Unlike [`Reflect`](crate::Reflect), it is object safe, so values can be
+/// kept as `Box<dyn DynFieldAccess>`.
+///
+/// ```
+/// use field_accessor::{DynFieldAccess, FieldAccessor};
+///
+/// #[derive(FieldAccessor)]
+/// struct Dog {
+///     name: String,
+/// }
+///
+/// #[derive(FieldAccessor)]
+/// struct Cat {
+///     name: String,
+///     lives: u8,
+/// }
+///
+/// let mut pets: Vec<Box<dyn DynFieldAccess>> = vec![
+///     Box::new(Dog { name: "Taro".to_string() }),
+///     Box::new(Cat { name: "Tama".to_string(), lives: 9 }),
+/// ];
+/// for pet in pets.iter_mut() {
+///     pet.set_dyn("name", Box::new("Pochi".to_string())).unwrap();
+/// }
+/// let name = pets[1].get_dyn("name").unwrap().downcast_ref::<String>();
+/// assert_eq!(name.map(String::as_str), Some("Pochi"));
+/// assert_eq!(pets[1].field_names(), ["name", "lives"]);
+/// ```
+pub trait DynFieldAccess {
+    /// Returns a reference to the field's value.
+    fn get_dyn(&self, field_string: &str) -> Result<&dyn Any, FieldAccessError>;
+
+    /// Returns a mutable reference to the field's value.
+    fn get_dyn_mut(&mut self, field_string: &str) -> Result<&mut dyn Any, FieldAccessError>;
+
+    /// Updates the field. If `value` does not hold the field's type, the error is a
+    /// `TypeMismatch` whose `expected` names the type of `value` if it is the type of one
+    /// of the fields, and is `"dyn Any"` otherwise.
+    fn set_dyn(&mut self, field_string: &str, value: Box<dyn Any>) -> Result<(), FieldAccessError>;
+
+    /// The names of the accessible fields. For enums, these are the fields of the active
+    /// variant.
+    fn field_names(&self) -> &'static [&'static str];
+
+    /// The name of the derived type.
+    fn type_name(&self) -> &'static str;
+}
@@ -0,0 +1,203 @@
+use std::error::Error;
+use std::fmt;
+
+/// The error returned by every generated accessor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FieldAccessError {
+    /// The struct has no field with this name.
+    UnknownField {
+        struct_name: &'static str,
+        field: String,
+    },
+    /// The field exists, but its type is not the one that was asked for.
+    TypeMismatch {
+        field: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The field belongs to an enum variant other than the active one.
+    FieldNotInVariant { variant: &'static str, field: String },
+    /// The field is marked `#[field_accessor(readonly)]` and cannot be modified.
+    ReadOnlyField { field: String },
+    /// A path tried to walk into a field that is not marked `#[field_accessor(nested)]`.
+    NotNested { field: String },
+    /// A path segment indexes a field that is not a sequence or map, or uses a
+    /// key where a position is expected (or the other way around).
+    NotIndexable { field: String, index: String },
+    /// A position past the end of a sequence field.
+    OutOfRange {
+        field: String,
+        index: usize,
+        len: usize,
+    },
+    /// A key that a map field does not contain.
+    MissingKey { field: String, key: String },
+    /// The path could not be parsed, e.g. because of an unclosed `[`.
+    MalformedPath { path: String },
+    /// Resolving `path` failed at `segment`.
+    InvalidPath {
+        path: String,
+        segment: String,
+        source: Box<FieldAccessError>,
+    },
+    /// `set_from_str` could not parse the input as the field's type.
+    ParseFailed {
+        field: String,
+        expected: &'static str,
+        message: String,
+    },
+    /// `set_json` could not deserialize the value as the field's type.
+    DeserializeFailed {
+        field: String,
+        expected: &'static str,
+        message: String,
+    },
+    /// `get_json` could not serialize the field, e.g. a map with non-string keys.
+    SerializeFailed { field: String, message: String },
+    /// The operation at `index` of a JSON Patch failed, so none of the patch was applied.
+    PatchFailed {
+        index: usize,
+        source: Box<FieldAccessError>,
+    },
+    /// A JSON Patch `test` operation found a different value at `path`.
+    TestFailed { path: String },
+    /// The field is marked `#[field_accessor(no_parse)]`, so it cannot be set from a string.
+    NotParsable { field: String },
+    /// Both fields exist, but they cannot be swapped because their types differ
+    /// or they are the same field.
+    IncompatibleSwap { field: String, field_y: String },
+    /// The new value of the field failed one of its validators, so it was not assigned.
+    ValidationFailed { field: String, reason: String },
+    /// A change was undone because the value no longer satisfied its invariant.
+    InvariantViolated { reason: String },
+    /// An operation names one field but holds the value of another.
+    FieldMismatch { field: String, found: &'static str },
+    /// Two values of an enum cannot be compared field by field, e.g. by `diff_json_patch`,
+    /// because they are different variants.
+    VariantMismatch {
+        variant: &'static str,
+        found: &'static str,
+    },
+    /// An operation takes a field whose type does not implement `Default`.
+    NoDefault { field: String },
+    /// A transaction was given a path, but it only changes whole fields.
+    PathNotSupported { path: String },
+    /// A struct could not be built from field values because one was not given.
+    MissingField {
+        struct_name: &'static str,
+        field: String,
+    },
+    /// A struct could not be built from field values because one was given twice.
+    DuplicateField { field: String },
+}
+
+impl fmt::Display for FieldAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldAccessError::UnknownField { struct_name, field } => {
+                write!(f, "no field named '{}' in '{}'", field, struct_name)
+            }
+            FieldAccessError::TypeMismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "field '{}' has type '{}', but '{}' was requested",
+                field, found, expected
+            ),
+            FieldAccessError::FieldNotInVariant { variant, field } => {
+                write!(f, "variant '{}' has no field named '{}'", variant, field)
+            }
+            FieldAccessError::ReadOnlyField { field } => {
+                write!(f, "field '{}' is read-only", field)
+            }
+            FieldAccessError::NotNested { field } => {
+                write!(f, "field '{}' is not nested and has no fields of its own", field)
+            }
+            FieldAccessError::NotIndexable { field, index } => {
+                write!(f, "field '{}' cannot be indexed by '{}'", field, index)
+            }
+            FieldAccessError::OutOfRange { field, index, len } => write!(
+                f,
+                "index {} is out of range for field '{}' of length {}",
+                index, field, len
+            ),
+            FieldAccessError::MissingKey { field, key } => {
+                write!(f, "field '{}' has no key '{}'", field, key)
+            }
+            FieldAccessError::MalformedPath { path } => write!(f, "malformed path '{}'", path),
+            FieldAccessError::InvalidPath { path, segment, .. } => {
+                write!(f, "invalid path '{}' at '{}'", path, segment)
+            }
+            FieldAccessError::ParseFailed {
+                field,
+                expected,
+                message,
+            } => write!(
+                f,
+                "cannot parse a '{}' for field '{}': {}",
+                expected, field, message
+            ),
+            FieldAccessError::DeserializeFailed {
+                field,
+                expected,
+                message,
+            } => write!(
+                f,
+                "cannot deserialize a '{}' for field '{}': {}",
+                expected, field, message
+            ),
+            FieldAccessError::SerializeFailed { field, message } => {
+                write!(f, "cannot serialize field '{}': {}", field, message)
+            }
+            FieldAccessError::PatchFailed { index, .. } => {
+                write!(f, "operation {} of the patch failed", index)
+            }
+            FieldAccessError::TestFailed { path } => {
+                write!(f, "test of '{}' failed", path)
+            }
+            FieldAccessError::NotParsable { field } => {
+                write!(f, "field '{}' cannot be set from a string", field)
+            }
+            FieldAccessError::IncompatibleSwap { field, field_y } => {
+                write!(f, "cannot swap field '{}' with field '{}'", field, field_y)
+            }
+            FieldAccessError::ValidationFailed { field, reason } => {
+                write!(f, "invalid value for field '{}': {}", field, reason)
+            }
+            FieldAccessError::InvariantViolated { reason } => {
+                write!(f, "invariant violated: {}", reason)
+            }
+            FieldAccessError::FieldMismatch { field, found } => {
+                write!(f, "expected a value for field '{}', found one for '{}'", field, found)
+            }
+            FieldAccessError::VariantMismatch { variant, found } => {
+                write!(f, "expected variant '{}', found variant '{}'", variant, found)
+            }
+            FieldAccessError::NoDefault { field } => {
+                write!(f, "field '{}' has no default value to take", field)
+            }
+            FieldAccessError::PathNotSupported { path } => {
+                write!(f, "'{}' is a path, but only field names are accepted here", path)
+            }
+            FieldAccessError::MissingField { struct_name, field } => {
+                write!(f, "no value for field '{}' of '{}'", field, struct_name)
+            }
+            FieldAccessError::DuplicateField { field } => {
+                write!(f, "more than one value for field '{}'", field)
+            }
+        }
+    }
+}
+
+impl Error for FieldAccessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FieldAccessError::InvalidPath { source, .. }
+            | FieldAccessError::PatchFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::any::{type_name, Any};
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+use crate::FieldAccessError;
+
+/// What a path segment such as `friends[1]` or `scores["alice"]` indexes a field with.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathIndex<'p> {
+    Position(usize),
+    Key(Cow<'p, str>),
+}
+
+impl fmt::Display for PathIndex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathIndex::Position(position) => write!(f, "[{}]", position),
+            PathIndex::Key(key) => write!(f, "[{:?}]", key),
+        }
+    }
+}
+
+/// Field types that a path can index into: sequences by position and maps with
+/// string-like keys by key.
+#[doc(hidden)]
+pub trait FieldIndex {
+    fn index_ref(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&dyn Any, &'static str), FieldAccessError>;
+
+    fn index_mut(
+        &mut self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&mut dyn Any, &'static str), FieldAccessError>;
+}
+
+/// Wraps a field so that the generated code can index it if its type implements
+/// `FieldIndex` and report `NotIndexable` otherwise.
+///
+/// `Indexed(field).index_ref(..)` resolves to [`IndexRef`] or [`IndexMut`], which take the
+/// wrapper by value, when the field type implements `FieldIndex`. Otherwise method lookup
+/// goes on to [`IndexFallback`], which takes it by reference.
+#[doc(hidden)]
+pub struct Indexed<T>(pub T);
+
+#[doc(hidden)]
+pub trait IndexRef<'f> {
+    fn index_ref(
+        self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&'f dyn Any, &'static str), FieldAccessError>;
+}
+
+impl<'f, T: FieldIndex + ?Sized> IndexRef<'f> for Indexed<&'f T> {
+    fn index_ref(
+        self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&'f dyn Any, &'static str), FieldAccessError> {
+        self.0.index_ref(field, index)
+    }
+}
+
+#[doc(hidden)]
+pub trait IndexMut<'f> {
+    fn index_mut(
+        self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&'f mut dyn Any, &'static str), FieldAccessError>;
+}
+
+impl<'f, T: FieldIndex + ?Sized> IndexMut<'f> for Indexed<&'f mut T> {
+    fn index_mut(
+        self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&'f mut dyn Any, &'static str), FieldAccessError> {
+        self.0.index_mut(field, index)
+    }
+}
+
+#[doc(hidden)]
+pub trait IndexFallback {
+    fn index_ref<'f>(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&'f dyn Any, &'static str), FieldAccessError> {
+        Err(not_indexable(field, index))
+    }
+
+    fn index_mut<'f>(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&'f mut dyn Any, &'static str), FieldAccessError> {
+        Err(not_indexable(field, index))
+    }
+}
+
+impl<T> IndexFallback for Indexed<T> {}
+
+fn not_indexable(field: &str, index: &PathIndex) -> FieldAccessError {
+    FieldAccessError::NotIndexable {
+        field: field.to_string(),
+        index: index.to_string(),
+    }
+}
+
+fn missing_key(field: &str, key: &str) -> FieldAccessError {
+    FieldAccessError::MissingKey {
+        field: field.to_string(),
+        key: key.to_string(),
+    }
+}
+
+impl<T: Any> FieldIndex for [T] {
+    fn index_ref(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&dyn Any, &'static str), FieldAccessError> {
+        let len = self.len();
+        match index {
+            PathIndex::Position(position) => match self.get(*position) {
+                Some(value) => Ok((value, type_name::<T>())),
+                None => Err(FieldAccessError::OutOfRange {
+                    field: field.to_string(),
+                    index: *position,
+                    len,
+                }),
+            },
+            PathIndex::Key(_) => Err(not_indexable(field, index)),
+        }
+    }
+
+    fn index_mut(
+        &mut self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&mut dyn Any, &'static str), FieldAccessError> {
+        let len = self.len();
+        match index {
+            PathIndex::Position(position) => match self.get_mut(*position) {
+                Some(value) => Ok((value, type_name::<T>())),
+                None => Err(FieldAccessError::OutOfRange {
+                    field: field.to_string(),
+                    index: *position,
+                    len,
+                }),
+            },
+            PathIndex::Key(_) => Err(not_indexable(field, index)),
+        }
+    }
+}
+
+/// Implements `FieldIndex` for a type that dereferences to a slice.
+macro_rules! index_as_slice {
+    ($(impl[$($param:tt)*] for $ty:ty;)*) => {$(
+        impl<$($param)*> FieldIndex for $ty {
+            fn index_ref(
+                &self,
+                field: &str,
+                index: &PathIndex,
+            ) -> Result<(&dyn Any, &'static str), FieldAccessError> {
+                self[..].index_ref(field, index)
+            }
+
+            fn index_mut(
+                &mut self,
+                field: &str,
+                index: &PathIndex,
+            ) -> Result<(&mut dyn Any, &'static str), FieldAccessError> {
+                self[..].index_mut(field, index)
+            }
+        }
+    )*};
+}
+
+index_as_slice! {
+    impl[T: Any] for Vec<T>;
+    impl[T: Any, const N: usize] for [T; N];
+    impl[T: Any] for Box<[T]>;
+    impl[T: Any] for &mut [T];
+}
+
+/// A shared slice can be read, but its elements cannot be modified.
+impl<T: Any> FieldIndex for &[T] {
+    fn index_ref(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&dyn Any, &'static str), FieldAccessError> {
+        (**self).index_ref(field, index)
+    }
+
+    fn index_mut(
+        &mut self,
+        field: &str,
+        _index: &PathIndex,
+    ) -> Result<(&mut dyn Any, &'static str), FieldAccessError> {
+        Err(FieldAccessError::ReadOnlyField {
+            field: field.to_string(),
+        })
+    }
+}
+
+impl<K, V, S> FieldIndex for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: Any,
+    S: BuildHasher,
+{
+    fn index_ref(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&dyn Any, &'static str), FieldAccessError> {
+        match index {
+            PathIndex::Key(key) => match self.get(key.as_ref()) {
+                Some(value) => Ok((value, type_name::<V>())),
+                None => Err(missing_key(field, key)),
+            },
+            PathIndex::Position(_) => Err(not_indexable(field, index)),
+        }
+    }
+
+    fn index_mut(
+        &mut self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&mut dyn Any, &'static str), FieldAccessError> {
+        match index {
+            PathIndex::Key(key) => match self.get_mut(key.as_ref()) {
+                Some(value) => Ok((value, type_name::<V>())),
+                None => Err(missing_key(field, key)),
+            },
+            PathIndex::Position(_) => Err(not_indexable(field, index)),
+        }
+    }
+}
+
+impl<K, V> FieldIndex for BTreeMap<K, V>
+where
+    K: Borrow<str> + Ord,
+    V: Any,
+{
+    fn index_ref(
+        &self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&dyn Any, &'static str), FieldAccessError> {
+        match index {
+            PathIndex::Key(key) => match self.get(key.as_ref()) {
+                Some(value) => Ok((value, type_name::<V>())),
+                None => Err(missing_key(field, key)),
+            },
+            PathIndex::Position(_) => Err(not_indexable(field, index)),
+        }
+    }
+
+    fn index_mut(
+        &mut self,
+        field: &str,
+        index: &PathIndex,
+    ) -> Result<(&mut dyn Any, &'static str), FieldAccessError> {
+        match index {
+            PathIndex::Key(key) => match self.get_mut(key.as_ref()) {
+                Some(value) => Ok((value, type_name::<V>())),
+                None => Err(missing_key(field, key)),
+            },
+            PathIndex::Position(_) => Err(not_indexable(field, index)),
+        }
+    }
+}
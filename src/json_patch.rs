@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::FieldAccessError;
+
+/// One operation of an RFC 6902 JSON Patch, as taken by `apply_json_patch` and returned
+/// by `diff_json_patch`.
+///
+/// Paths are RFC 6901 JSON Pointers into the JSON form of the fields, such as `/age`,
+/// `/friends/0` or `/scores/alice`. A patch is deserialized from the usual
+/// `[{"op": "replace", "path": "/age", "value": 4}]` form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// The fields of a derived value in their JSON form, which a patch is applied to before
+/// any of them is written back.
+#[doc(hidden)]
+pub struct JsonFields<'a> {
+    pub struct_name: &'static str,
+    pub readonly: &'a [&'static str],
+    pub fields: Map<String, Value>,
+}
+
+/// A parsed JSON Pointer. The root pointer `""` is rejected, since a patch can only
+/// change fields, not replace the whole value.
+struct Pointer<'p> {
+    path: &'p str,
+    tokens: Vec<String>,
+}
+
+impl<'p> Pointer<'p> {
+    fn parse(path: &'p str) -> Result<Self, FieldAccessError> {
+        let malformed = || FieldAccessError::MalformedPath {
+            path: path.to_string(),
+        };
+        let tokens = path
+            .strip_prefix('/')
+            .ok_or_else(malformed)?
+            .split('/')
+            .map(|token| unescape(token).ok_or_else(malformed))
+            .collect::<Result<_, _>>()?;
+        Ok(Pointer { path, tokens })
+    }
+
+    fn invalid(&self, token: &str, source: FieldAccessError) -> FieldAccessError {
+        FieldAccessError::InvalidPath {
+            path: self.path.to_string(),
+            segment: token.to_string(),
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Decodes `~1` and `~0` in a pointer token; any other `~` is malformed.
+fn unescape(token: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(match c {
+            '~' => match chars.next()? {
+                '0' => '~',
+                '1' => '/',
+                _ => return None,
+            },
+            c => c,
+        });
+    }
+    Some(unescaped)
+}
+
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parses an array index, which RFC 6901 writes without leading zeros.
+fn parse_position(token: &str) -> Option<usize> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Resolves `token` within `value`, whose own token is `parent`.
+fn child_mut<'v>(
+    value: &'v mut Value,
+    parent: &str,
+    token: &str,
+) -> Result<&'v mut Value, FieldAccessError> {
+    match value {
+        Value::Object(map) => map
+            .get_mut(token)
+            .ok_or_else(|| FieldAccessError::MissingKey {
+                field: parent.to_string(),
+                key: token.to_string(),
+            }),
+        Value::Array(items) => {
+            let len = items.len();
+            let index = parse_position(token).ok_or_else(|| not_indexable(parent, token))?;
+            items.get_mut(index).ok_or(FieldAccessError::OutOfRange {
+                field: parent.to_string(),
+                index,
+                len,
+            })
+        }
+        _ => Err(not_indexable(parent, token)),
+    }
+}
+
+fn not_indexable(parent: &str, token: &str) -> FieldAccessError {
+    FieldAccessError::NotIndexable {
+        field: parent.to_string(),
+        index: token.to_string(),
+    }
+}
+
+impl JsonFields<'_> {
+    /// Applies every operation in order, stopping at the first one that fails.
+    pub fn apply(&mut self, patch: &[PatchOperation]) -> Result<(), FieldAccessError> {
+        for (index, operation) in patch.iter().enumerate() {
+            self.apply_one(operation)
+                .map_err(|source| FieldAccessError::PatchFailed {
+                    index,
+                    source: Box::new(source),
+                })?;
+        }
+        Ok(())
+    }
+
+    fn apply_one(&mut self, operation: &PatchOperation) -> Result<(), FieldAccessError> {
+        match operation {
+            PatchOperation::Add { path, value } => self.add(&Pointer::parse(path)?, value.clone()),
+            PatchOperation::Remove { path } => self.remove(&Pointer::parse(path)?).map(drop),
+            PatchOperation::Replace { path, value } => {
+                let path = Pointer::parse(path)?;
+                self.check_writable(&path)?;
+                *self.lookup(&path)? = value.clone();
+                Ok(())
+            }
+            PatchOperation::Move { from, path } => {
+                let (from, path) = (Pointer::parse(from)?, Pointer::parse(path)?);
+                if from.tokens == path.tokens {
+                    return self.lookup(&from).map(drop);
+                }
+                let value = self.remove(&from)?;
+                self.add(&path, value)
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = self.lookup(&Pointer::parse(from)?)?.clone();
+                self.add(&Pointer::parse(path)?, value)
+            }
+            PatchOperation::Test { path, value } => {
+                if self.lookup(&Pointer::parse(path)?)? == value {
+                    Ok(())
+                } else {
+                    Err(FieldAccessError::TestFailed { path: path.clone() })
+                }
+            }
+        }
+    }
+
+    /// Resolves the whole pointer.
+    fn lookup(&mut self, pointer: &Pointer) -> Result<&mut Value, FieldAccessError> {
+        self.resolve(pointer, &pointer.tokens)
+    }
+
+    /// Resolves the first of `tokens`, which must be a field, and then the rest of them.
+    fn resolve(
+        &mut self,
+        pointer: &Pointer,
+        tokens: &[String],
+    ) -> Result<&mut Value, FieldAccessError> {
+        let (field, rest) = tokens.split_first().expect("pointers have a token");
+        let struct_name = self.struct_name;
+        let mut value = self.fields.get_mut(field).ok_or_else(|| {
+            pointer.invalid(
+                field,
+                FieldAccessError::UnknownField {
+                    struct_name,
+                    field: field.clone(),
+                },
+            )
+        })?;
+        let mut parent = field;
+        for token in rest {
+            value =
+                child_mut(value, parent, token).map_err(|source| pointer.invalid(token, source))?;
+            parent = token;
+        }
+        Ok(value)
+    }
+
+    fn check_writable(&self, pointer: &Pointer) -> Result<(), FieldAccessError> {
+        let field = &pointer.tokens[0];
+        if self.readonly.contains(&field.as_str()) {
+            return Err(pointer.invalid(
+                field,
+                FieldAccessError::ReadOnlyField {
+                    field: field.clone(),
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds a member to an object or inserts an element into an array. A field always
+    /// exists, so adding one replaces its value.
+    fn add(&mut self, pointer: &Pointer, value: Value) -> Result<(), FieldAccessError> {
+        self.check_writable(pointer)?;
+        let (token, parents) = pointer.tokens.split_last().expect("pointers have a token");
+        if parents.is_empty() {
+            *self.lookup(pointer)? = value;
+            return Ok(());
+        }
+        let parent = parents.last().unwrap();
+        match self.resolve(pointer, parents)? {
+            Value::Object(map) => {
+                map.insert(token.clone(), value);
+            }
+            Value::Array(items) if token == "-" => items.push(value),
+            Value::Array(items) => {
+                let len = items.len();
+                let index = parse_position(token)
+                    .ok_or_else(|| pointer.invalid(token, not_indexable(parent, token)))?;
+                if index > len {
+                    let source = FieldAccessError::OutOfRange {
+                        field: parent.clone(),
+                        index,
+                        len,
+                    };
+                    return Err(pointer.invalid(token, source));
+                }
+                items.insert(index, value);
+            }
+            _ => return Err(pointer.invalid(token, not_indexable(parent, token))),
+        }
+        Ok(())
+    }
+
+    /// Removes a member of an object or an element of an array. Fields cannot be removed.
+    fn remove(&mut self, pointer: &Pointer) -> Result<Value, FieldAccessError> {
+        self.check_writable(pointer)?;
+        let (token, parents) = pointer.tokens.split_last().expect("pointers have a token");
+        if parents.is_empty() {
+            self.lookup(pointer)?;
+            let source = FieldAccessError::MissingField {
+                struct_name: self.struct_name,
+                field: token.clone(),
+            };
+            return Err(pointer.invalid(token, source));
+        }
+        let parent = parents.last().unwrap();
+        let removed = match self.resolve(pointer, parents)? {
+            Value::Object(map) => map
+                .remove(token)
+                .ok_or_else(|| FieldAccessError::MissingKey {
+                    field: parent.clone(),
+                    key: token.clone(),
+                }),
+            Value::Array(items) => match parse_position(token) {
+                Some(index) if index < items.len() => Ok(items.remove(index)),
+                Some(index) => Err(FieldAccessError::OutOfRange {
+                    field: parent.clone(),
+                    index,
+                    len: items.len(),
+                }),
+                None => Err(not_indexable(parent, token)),
+            },
+            _ => Err(not_indexable(parent, token)),
+        };
+        removed.map_err(|source| pointer.invalid(token, source))
+    }
+
+    /// Takes the patched value of `field` if it differs from `original`.
+    pub fn take_changed(&mut self, original: &Map<String, Value>, field: &str) -> Option<Value> {
+        let value = self.fields.remove(field)?;
+        (original.get(field) != Some(&value)).then_some(value)
+    }
+}
+
+/// The operations that turn the fields `from` into the fields `to`.
+#[doc(hidden)]
+pub fn diff(from: &Map<String, Value>, to: &Map<String, Value>) -> Vec<PatchOperation> {
+    let mut patch = vec![];
+    diff_objects(&mut patch, "", from, to);
+    patch
+}
+
+fn diff_objects(
+    patch: &mut Vec<PatchOperation>,
+    path: &str,
+    from: &Map<String, Value>,
+    to: &Map<String, Value>,
+) {
+    for (key, value) in from {
+        let path = format!("{}/{}", path, escape(key));
+        match to.get(key) {
+            Some(other) => diff_values(patch, path, value, other),
+            None => patch.push(PatchOperation::Remove { path }),
+        }
+    }
+    for (key, value) in to {
+        if !from.contains_key(key) {
+            patch.push(PatchOperation::Add {
+                path: format!("{}/{}", path, escape(key)),
+                value: value.clone(),
+            });
+        }
+    }
+}
+
+fn diff_values(patch: &mut Vec<PatchOperation>, path: String, from: &Value, to: &Value) {
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => diff_objects(patch, &path, from, to),
+        (Value::Array(from), Value::Array(to)) => {
+            let common = from.len().min(to.len());
+            for (i, (a, b)) in from.iter().zip(to).enumerate() {
+                diff_values(patch, format!("{}/{}", path, i), a, b);
+            }
+            // Remove from the end, so that earlier positions stay valid.
+            for i in (common..from.len()).rev() {
+                patch.push(PatchOperation::Remove {
+                    path: format!("{}/{}", path, i),
+                });
+            }
+            for (i, value) in to.iter().enumerate().skip(common) {
+                patch.push(PatchOperation::Add {
+                    path: format!("{}/{}", path, i),
+                    value: value.clone(),
+                });
+            }
+        }
+        _ if from != to => patch.push(PatchOperation::Replace {
+            path,
+            value: to.clone(),
+        }),
+        _ => {}
+    }
+}
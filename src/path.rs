@@ -0,0 +1,208 @@
+use std::any::{type_name, Any};
+use std::borrow::Cow;
+
+use crate::index::PathIndex;
+use crate::FieldAccessError;
+
+/// One dot-separated part of a path: a field name, optionally followed by an index.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment<'p> {
+    /// The segment as written, e.g. `scores["alice"]`.
+    pub text: &'p str,
+    pub name: &'p str,
+    pub index: Option<PathIndex<'p>>,
+    /// Where the segment starts in the path.
+    pub start: usize,
+}
+
+impl<'p> Segment<'p> {
+    /// Joins this segment with `next` if together they name one of `fields`, as the
+    /// fields of tuple variants do, e.g. `Resize` and `0` name `Resize.0`.
+    pub fn join(&self, path: &'p str, next: &Segment<'p>, fields: &[&str]) -> Option<Segment<'p>> {
+        if self.index.is_some() {
+            return None;
+        }
+        let name = &path[self.start..next.start + next.name.len()];
+        fields.contains(&name).then(|| Segment {
+            text: &path[self.start..next.start + next.text.len()],
+            name,
+            index: next.index.clone(),
+            start: self.start,
+        })
+    }
+}
+
+/// Access to fields through paths such as `"data.some_value"` or `"friends[1]"`.
+///
+/// `#[derive(FieldAccessor)]` implements this trait. A path walks into a field only if
+/// it is marked `#[field_accessor(nested)]`, whose type must derive `FieldAccessor` too.
+/// The last segment may index into a `Vec`, array or slice field by position, or into a
+/// `HashMap` or `BTreeMap` field with string-like keys by a quoted key.
+/// The derived type also gets inherent `get_path`, `get_path_mut`, `set_path`,
+/// `take_path` and `getenum_path` methods, so the trait only needs to be imported for
+/// generic code.
+pub trait FieldPath {
+    #[doc(hidden)]
+    fn __path_ref(
+        &self,
+        path: &str,
+        segment: &Segment,
+        rest: &[Segment],
+    ) -> Result<(&dyn Any, &'static str), FieldAccessError>;
+
+    #[doc(hidden)]
+    fn __path_mut(
+        &mut self,
+        path: &str,
+        segment: &Segment,
+        rest: &[Segment],
+    ) -> Result<(&mut dyn Any, &'static str), FieldAccessError>;
+
+    /// Returns a reference to the field at `path`.
+    fn get_path<T: Any>(&self, path: &str) -> Result<&T, FieldAccessError> {
+        let segments = parse_path(path)?;
+        let (value, found) = self.__path_ref(path, &segments[0], &segments[1..])?;
+        value
+            .downcast_ref()
+            .ok_or_else(|| type_mismatch::<T>(path, found))
+    }
+
+    /// Returns a mutable reference to the field at `path`.
+    fn get_path_mut<T: Any>(&mut self, path: &str) -> Result<&mut T, FieldAccessError> {
+        let segments = parse_path(path)?;
+        let (value, found) = self.__path_mut(path, &segments[0], &segments[1..])?;
+        value
+            .downcast_mut()
+            .ok_or_else(|| type_mismatch::<T>(path, found))
+    }
+
+    /// Updates the field at `path`. The derive checks the validators of the field the path
+    /// starts at and the invariant, and restores the previous value if they fail.
+    fn set_path<T: Any>(&mut self, path: &str, value: T) -> Result<(), FieldAccessError> {
+        *self.get_path_mut(path)? = value;
+        Ok(())
+    }
+
+    /// Replaces the field at `path` with the default value of `T`, returning the previous
+    /// value.
+    fn take_path<T: Any + Default>(&mut self, path: &str) -> Result<T, FieldAccessError> {
+        Ok(std::mem::take(self.get_path_mut(path)?))
+    }
+
+    /// Returns the field at `path` as the `FieldEnum` of the struct that holds it.
+    ///
+    /// Indexed elements have no `FieldEnum`, so the path cannot end in an index. Only
+    /// available if the fields along the path can be cloned.
+    fn getenum_path<E: Any>(&self, path: &str) -> Result<E, FieldAccessError>
+    where
+        Self: FieldPathEnum,
+    {
+        let segments = parse_path(path)?;
+        let (value, found) = self.__path_enum(path, &segments[0], &segments[1..])?;
+        value
+            .downcast()
+            .map(|value| *value)
+            .map_err(|_| type_mismatch::<E>(path, found))
+    }
+}
+
+/// The part of [`FieldPath`] that clones fields, implemented only if they can be cloned.
+#[doc(hidden)]
+pub trait FieldPathEnum {
+    fn __path_enum(
+        &self,
+        path: &str,
+        segment: &Segment,
+        rest: &[Segment],
+    ) -> Result<(Box<dyn Any>, &'static str), FieldAccessError>;
+}
+
+/// The name of the field `path` starts at, e.g. `friends` for `friends[1]`, or `path`
+/// itself if it starts at none of `names`.
+#[doc(hidden)]
+pub fn path_field<'p>(path: &'p str, names: &[&str]) -> &'p str {
+    names
+        .iter()
+        .find(|name| {
+            path.strip_prefix(**name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['[', '.']))
+        })
+        .map_or(path, |name| &path[..name.len()])
+}
+
+fn type_mismatch<T>(path: &str, found: &'static str) -> FieldAccessError {
+    FieldAccessError::TypeMismatch {
+        field: path.to_string(),
+        expected: type_name::<T>(),
+        found,
+    }
+}
+
+/// Splits `path` into its segments, e.g. `data.scores["alice"]` into `data` and
+/// `scores` indexed by `"alice"`. Keys may contain `\"` and `\\` escapes.
+fn parse_path(path: &str) -> Result<Vec<Segment<'_>>, FieldAccessError> {
+    let malformed = || FieldAccessError::MalformedPath {
+        path: path.to_string(),
+    };
+    let mut segments = vec![];
+    let mut start = 0;
+    loop {
+        let name_end = path[start..]
+            .find(['.', '['])
+            .map_or(path.len(), |i| start + i);
+        let name = &path[start..name_end];
+        if name.is_empty() {
+            return Err(malformed());
+        }
+        let (index, end) = match path[name_end..].strip_prefix('[') {
+            Some(bracketed) => {
+                let (index, len) = parse_index(bracketed).ok_or_else(malformed)?;
+                (Some(index), name_end + 1 + len)
+            }
+            None => (None, name_end),
+        };
+        segments.push(Segment {
+            text: &path[start..end],
+            name,
+            index,
+            start,
+        });
+        match path[end..].strip_prefix('.') {
+            Some(_) => start = end + 1,
+            None if end == path.len() => return Ok(segments),
+            None => return Err(malformed()),
+        }
+    }
+}
+
+/// Parses what follows a `[` up to and including the matching `]`, returning the index
+/// and the number of bytes consumed.
+fn parse_index(bracketed: &str) -> Option<(PathIndex<'_>, usize)> {
+    let Some(quoted) = bracketed.strip_prefix('"') else {
+        let len = bracketed.find(']')?;
+        let position = bracketed[..len].parse().ok()?;
+        return Some((PathIndex::Position(position), len + 1));
+    };
+    let mut key: Option<String> = None;
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                quoted[i + 1..].strip_prefix(']')?;
+                let key = key.map_or(Cow::Borrowed(&quoted[..i]), Cow::Owned);
+                return Some((PathIndex::Key(key), i + 3));
+            }
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                key.get_or_insert_with(|| quoted[..i].to_string()).push(escaped);
+            }
+            c => {
+                if let Some(key) = &mut key {
+                    key.push(c);
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,71 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use strum::IntoEnumIterator;
+
+use crate::FieldAccessError;
+
+/// The accessors of `#[derive(FieldAccessor)]`, for code that is generic over the
+/// derived type.
+///
+/// The derive implements this trait if every field type implements `Clone`, since
+/// `getenum` clones the field it returns.
+///
+/// ```
+/// use field_accessor::{FieldAccessor, Reflect};
+///
+/// fn dump<S: Reflect>(s: &S) -> Vec<String>
+/// where
+///     S::FieldEnum: std::fmt::Debug,
+/// {
+///     S::field_names()
+///         .iter()
+///         .map(|name| format!("{:?}", s.getenum(name).unwrap()))
+///         .collect()
+/// }
+///
+/// #[derive(FieldAccessor)]
+/// struct Dog {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// let dog = Dog { name: "Taro".to_string(), age: 3 };
+/// assert_eq!(<Dog as Reflect>::struct_name(), "Dog");
+/// assert_eq!(dump(&dog), ["name(\"Taro\")", "age(3)"]);
+/// ```
+pub trait Reflect {
+    /// `(StructName)FieldEnum`, with one variant holding each field's value.
+    type FieldEnum;
+    /// `(StructName)Fields`, with one variant naming each field.
+    type Fields: Clone
+        + Eq
+        + Ord
+        + Hash
+        + Debug
+        + AsRef<str>
+        + FromStr<Err = strum::ParseError>
+        + IntoEnumIterator;
+    /// `(StructName)Types`, with one variant for each distinct field type.
+    type Types: Clone + Debug;
+    /// `(StructName)StructInfo`, as returned by `getstructinfo`.
+    type StructInfo: Clone + Debug;
+
+    /// The name of the derived type.
+    fn struct_name() -> &'static str;
+
+    /// The names of the accessible fields. For enums, these are the fields of every variant.
+    fn field_names() -> &'static [&'static str];
+
+    /// The type of each field in `field_names`.
+    fn field_types() -> &'static [Self::Types];
+
+    /// Returns a clone of the field's value.
+    fn getenum(&self, field_string: &str) -> Result<Self::FieldEnum, FieldAccessError>;
+
+    /// Assigns the field that matches the variant of `value`.
+    fn setenum(&mut self, value: Self::FieldEnum) -> Result<(), FieldAccessError>;
+
+    fn getstructinfo(&self) -> Self::StructInfo;
+}
@@ -1,9 +1,6 @@
 #[cfg(test)]
 mod tests_simple_struct {
-    use strum_macros::EnumIter;
-use field_accessor::FieldAccessor;
-    use strum_macros::AsRefStr;
-    use strum_macros::EnumString;
+    use field_accessor::{FieldAccessError, FieldAccessor};
 
     #[derive(FieldAccessor)]
     pub struct Dog {
@@ -23,7 +20,7 @@ use field_accessor::FieldAccessor;
         let value_to_update = "Jiro".to_string();
         dog.set(&field_name, value_to_update).unwrap();
         let value_on_error;
-        let fieldvalue: &String = match dog.get(&"invalid_name".to_string()) {
+        let fieldvalue: &String = match dog.get("invalid_name") {
             Ok(value) => value,
             Err(_) => {
                 value_on_error = "Ken".to_string();
@@ -40,7 +37,7 @@ use field_accessor::FieldAccessor;
             age: 3,
             life_expectancy: 9,
         };
-        let v: &mut String = dog.get_mut(&"name".to_string()).unwrap();
+        let v: &mut String = dog.get_mut("name").unwrap();
         *v = "Jiro".to_string();
         assert_eq!(dog.name, "Jiro".to_string());
     }
@@ -67,8 +64,36 @@ use field_accessor::FieldAccessor;
             life_expectancy: 9,
         };
         let field_name = "favorite_food".to_string();
-        let fieldvalue: Result<&String, String> = dog.get(&field_name);
-        assert!(fieldvalue.is_err());
+        let fieldvalue: Result<&String, FieldAccessError> = dog.get(&field_name);
+        assert_eq!(
+            fieldvalue,
+            Err(FieldAccessError::UnknownField {
+                struct_name: "Dog",
+                field: "favorite_food".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_type_mismatch() {
+        let dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            life_expectancy: 9,
+        };
+        let fieldvalue: Result<&String, FieldAccessError> = dog.get("age");
+        assert_eq!(
+            fieldvalue,
+            Err(FieldAccessError::TypeMismatch {
+                field: "age".to_string(),
+                expected: "String",
+                found: "u32",
+            })
+        );
+        assert_eq!(
+            fieldvalue.unwrap_err().to_string(),
+            "field 'age' has type 'u32', but 'String' was requested"
+        );
     }
 
     #[test]
@@ -95,10 +120,7 @@ use field_accessor::FieldAccessor;
 
 #[cfg(test)]
 mod test_mem {
-    use strum_macros::EnumIter;
-use field_accessor::FieldAccessor;
-    use strum_macros::AsRefStr;
-    use strum_macros::EnumString;
+    use field_accessor::{FieldAccessError, FieldAccessor};
 
     #[derive(FieldAccessor)]
     pub struct Dog {
@@ -152,14 +174,36 @@ use field_accessor::FieldAccessor;
         assert_eq!(dog.age, 9);
         assert_eq!(dog.life_expectancy, 3);
     }
+
+    #[test]
+    fn test_swap_errors() {
+        let mut dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            life_expectancy: 9,
+            friends: vec!["Mike".to_string(), "Nozomi".to_string()],
+        };
+        assert_eq!(
+            dog.swap("age", "name"),
+            Err(FieldAccessError::IncompatibleSwap {
+                field: "age".to_string(),
+                field_y: "name".to_string(),
+            })
+        );
+        assert_eq!(
+            dog.swap("age", "weight"),
+            Err(FieldAccessError::UnknownField {
+                struct_name: "Dog",
+                field: "weight".to_string(),
+            })
+        );
+        assert_eq!(dog.age, 3);
+    }
 }
 
 #[cfg(test)]
 mod tests_vector_type {
-    use strum_macros::EnumIter;
-use field_accessor::FieldAccessor;
-    use strum_macros::AsRefStr;
-    use strum_macros::EnumString;
+    use field_accessor::FieldAccessor;
 
     #[derive(FieldAccessor)]
     pub struct Dog {
@@ -187,10 +231,7 @@ use field_accessor::FieldAccessor;
 
 #[cfg(test)]
 mod tests_getstructinfo {
-    use strum_macros::EnumIter;
-use field_accessor::FieldAccessor;
-    use strum_macros::AsRefStr;
-    use strum_macros::EnumString;
+    use field_accessor::FieldAccessor;
 
     #[derive(FieldAccessor)]
     pub struct Dog {
@@ -215,14 +256,36 @@ use field_accessor::FieldAccessor;
         assert_eq!(fieldvalues[1], DogFieldEnum::age(3));
         assert_eq!(fieldvalues[2], DogFieldEnum::life_expectancy(9));
     }
+
+    #[test]
+    fn test_fn_pointer_fields() {
+        #[derive(FieldAccessor)]
+        struct Handlers {
+            on_byte: fn(u8) -> u8,
+            on_pair: fn(u8, u8) -> u8,
+            raw: *const u8,
+        }
+
+        let handlers = Handlers {
+            on_byte: |b| b + 1,
+            on_pair: |a, b| a * b,
+            raw: std::ptr::null(),
+        };
+        let on_byte: &fn(u8) -> u8 = handlers.get("on_byte").unwrap();
+        assert_eq!(on_byte(1), 2);
+        let on_pair: &fn(u8, u8) -> u8 = handlers.get("on_pair").unwrap();
+        assert_eq!(on_pair(2, 3), 6);
+        let raw: &*const u8 = handlers.get("raw").unwrap();
+        assert!(raw.is_null());
+        assert!(matches!(
+            HandlersTypeList,
+            [HandlersTypes::Type_fnu8Returnsu8, HandlersTypes::Type_fnu8Andu8Returnsu8, HandlersTypes::Type__constu8]
+        ));
+    }
 }
 
 #[cfg(test)]
 mod tests_multiple_derive {
-    use strum_macros::EnumIter;
-use strum_macros::AsRefStr;
-    use strum_macros::EnumString;
-
     #[test]
     fn test_multiple_derive() {
         use field_accessor::FieldAccessor;
@@ -236,15 +299,22 @@ use strum_macros::AsRefStr;
         struct Test2 {
             pub name: String,
         }
+
+        let test = Test {
+            name: "Taro".to_string(),
+        };
+        let test2 = Test2 {
+            name: "Jiro".to_string(),
+        };
+        let name: &String = test.get("name").unwrap();
+        let name2: &String = test2.get("name").unwrap();
+        assert_ne!(name, name2);
     }
 }
 
 #[cfg(test)]
 mod tests_nested_structs {
-    use strum_macros::EnumIter;
-use strum::IntoEnumIterator;
-    use strum_macros::AsRefStr;
-    use strum_macros::EnumString;
+    use field_accessor::IntoEnumIterator;
 
     #[test]
     fn test_nested_structs() {
@@ -273,7 +343,7 @@ use strum::IntoEnumIterator;
         };
         let field_name = "name".to_string();
         let name: &String = my_user.get(&field_name).unwrap();
-        let userdata: &UserData = my_user.get(&"data".to_string()).unwrap();
+        let userdata: &UserData = my_user.get("data").unwrap();
         assert_eq!(*name, "aGoodName".to_string());
         assert_eq!(*userdata.some_field, "some value".to_string());
     }
@@ -305,10 +375,2532 @@ use strum::IntoEnumIterator;
         };
         let field_name = UserFields::name.as_ref().to_string();
         let name: &String = my_user.get(&field_name).unwrap();
-        let userdata: &UserData = my_user.get(&UserFields::data.as_ref().to_string()).unwrap();
+        let userdata: &UserData = my_user.get(UserFields::data.as_ref()).unwrap();
         assert_eq!(*name, "aGoodName".to_string());
         assert_eq!(*userdata.some_field, "some value".to_string());
 
         assert_eq!(UserFields::iter().collect::<Vec<_>>().len(), UserTypeList.len());
     }
 }
+
+#[cfg(test)]
+mod tests_generics {
+    use std::collections::HashMap;
+
+    use field_accessor::FieldAccessor;
+
+    #[derive(FieldAccessor)]
+    pub struct Wrapper<T> {
+        inner: T,
+        count: u32,
+        backup: T,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Borrowed<'a, const N: usize>
+    where
+        [u8; N]: Default,
+    {
+        label: &'a str,
+        bytes: [u8; N],
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Roster<T> {
+        members: Vec<T>,
+        scores: HashMap<String, u32>,
+    }
+
+    #[derive(PartialEq, PartialOrd, Clone, Debug)]
+    pub struct NotDefault(u8);
+
+    #[test]
+    fn test_generic_get_set() {
+        let mut wrapper = Wrapper {
+            inner: "Taro".to_string(),
+            count: 1,
+            backup: "Jiro".to_string(),
+        };
+        let inner: &String = wrapper.get("inner").unwrap();
+        assert_eq!(inner, "Taro");
+        wrapper.set("count", 2u32).unwrap();
+        assert_eq!(wrapper.count, 2);
+        wrapper.swap("inner", "backup").unwrap();
+        assert_eq!(wrapper.inner, "Jiro");
+        assert_eq!(
+            wrapper.getenum("backup").unwrap(),
+            WrapperFieldEnum::backup("Taro".to_string())
+        );
+    }
+
+    #[test]
+    fn test_generic_without_default() {
+        let mut wrapper = Wrapper {
+            inner: NotDefault(1),
+            count: 1,
+            backup: NotDefault(2),
+        };
+        wrapper.set("inner", NotDefault(3)).unwrap();
+        let inner: &NotDefault = wrapper.get("inner").unwrap();
+        assert_eq!(inner, &NotDefault(3));
+        let count: u32 = wrapper.take("count").unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_generic_paths() {
+        let mut roster = Roster {
+            members: vec!["Taro".to_string(), "Jiro".to_string()],
+            scores: HashMap::from([("taro".to_string(), 1)]),
+        };
+        let member: &String = roster.get("members[1]").unwrap();
+        assert_eq!(member, "Jiro");
+        roster.set("members[0]", "Saburo".to_string()).unwrap();
+        let score: &mut u32 = roster.get_mut("scores[\"taro\"]").unwrap();
+        *score = 5;
+        let taken: String = roster.take("members[1]").unwrap();
+        assert_eq!(taken, "Jiro");
+        let previous: String = roster.replace("members[1]", "Shiro".to_string()).unwrap();
+        assert_eq!(previous, "");
+        assert_eq!(roster.members, ["Saburo", "Shiro"]);
+        assert_eq!(roster.scores["taro"], 5);
+    }
+
+    #[test]
+    fn test_lifetime_and_const_generics() {
+        let text = String::from("label");
+        let mut borrowed = Borrowed {
+            label: &text,
+            bytes: [1, 2, 3],
+        };
+        let label: &&str = borrowed.get("label").unwrap();
+        assert_eq!(*label, "label");
+        let bytes: [u8; 3] = borrowed.take("bytes").unwrap();
+        assert_eq!(bytes, [1, 2, 3]);
+        assert_eq!(borrowed.bytes, [0, 0, 0]);
+        assert_eq!(
+            borrowed.getenum("label").unwrap(),
+            BorrowedFieldEnum::label("label")
+        );
+        let info = borrowed.getstructinfo();
+        assert_eq!(info.struct_name, "Borrowed");
+        assert_eq!(info.field_names, vec!["label", "bytes"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_tuple_struct {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor)]
+    pub struct Rgb(u8, u8, u8);
+
+    #[derive(FieldAccessor)]
+    pub struct Meters(f64);
+
+    #[test]
+    fn test_get_set_by_index() {
+        let mut color = Rgb(10, 20, 30);
+        let green: &u8 = color.get("1").unwrap();
+        assert_eq!(*green, 20);
+        color.set("2", 40).unwrap();
+        assert_eq!(color.2, 40);
+        color.swap("0", "1").unwrap();
+        assert_eq!((color.0, color.1), (20, 10));
+        let missing: Result<&u8, FieldAccessError> = color.get("3");
+        assert_eq!(
+            missing,
+            Err(FieldAccessError::UnknownField {
+                struct_name: "Rgb",
+                field: "3".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_enums_and_structinfo() {
+        let meters = Meters(1.5);
+        assert_eq!(meters.getenum("0").unwrap(), MetersFieldEnum::_0(1.5));
+        assert_eq!(RgbFields::_2.as_ref(), "2");
+        assert_eq!("1".parse::<RgbFields>().unwrap(), RgbFields::_1);
+        let info = Rgb(0, 0, 0).getstructinfo();
+        assert_eq!(info.field_names, vec!["0", "1", "2"]);
+        assert_eq!(info.field_types, vec!["u8", "u8", "u8"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_enum {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor)]
+    pub enum Event {
+        Click { x: i32, y: i32 },
+        Key { code: u32, x: i32 },
+        Resize(u32, u32),
+        Quit,
+    }
+
+    #[test]
+    fn test_get_set_on_active_variant() {
+        let mut event = Event::Click { x: 1, y: 2 };
+        let x: &i32 = event.get("x").unwrap();
+        assert_eq!(*x, 1);
+        event.set("y", 5).unwrap();
+        event.swap("x", "y").unwrap();
+        let y: &mut i32 = event.get_mut("y").unwrap();
+        *y += 1;
+        assert_eq!(event.getenum("x").unwrap(), EventFieldEnum::x(5));
+        assert_eq!(event.getenum("y").unwrap(), EventFieldEnum::y(2));
+
+        let mut event = Event::Resize(640, 480);
+        let height: u32 = event.replace("Resize.1", 720).unwrap();
+        assert_eq!(height, 480);
+        assert_eq!(event.getenum("Resize.1").unwrap(), EventFieldEnum::Resize_1(720));
+    }
+
+    #[test]
+    fn test_field_of_inactive_variant() {
+        let mut event = Event::Key { code: 13, x: 0 };
+        let y: Result<&i32, FieldAccessError> = event.get("y");
+        assert_eq!(
+            y,
+            Err(FieldAccessError::FieldNotInVariant {
+                variant: "Key",
+                field: "y".to_string(),
+            })
+        );
+        assert_eq!(
+            event.set("z", 1i32),
+            Err(FieldAccessError::UnknownField {
+                struct_name: "Event",
+                field: "z".to_string(),
+            })
+        );
+        assert_eq!(
+            Event::Quit.getenum("code"),
+            Err(FieldAccessError::FieldNotInVariant {
+                variant: "Quit",
+                field: "code".to_string(),
+            })
+        );
+        assert_eq!(
+            event.swap("code", "x"),
+            Err(FieldAccessError::IncompatibleSwap {
+                field: "code".to_string(),
+                field_y: "x".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_variant_info() {
+        let event = Event::Key { code: 13, x: 0 };
+        assert_eq!(event.variant_name(), "Key");
+        let info = event.getstructinfo();
+        assert_eq!(info.struct_name, "Event");
+        assert_eq!(info.field_names, vec!["x", "y", "code", "Resize.0", "Resize.1"]);
+
+        let active = event.getvariantinfo();
+        assert_eq!(active.variant_name, "Key");
+        assert_eq!(active.field_names, vec!["code", "x"]);
+        assert_eq!(active.field_types, vec!["u32", "i32"]);
+
+        let infos = event.getvariantinfos();
+        assert_eq!(infos.len(), 4);
+        assert_eq!(infos[2].field_names, vec!["Resize.0", "Resize.1"]);
+        assert!(infos[3].field_names.is_empty());
+    }
+
+    #[test]
+    fn test_tuple_variants_of_different_types() {
+        #[derive(FieldAccessor)]
+        enum Value {
+            Text(String),
+            Number(i64),
+        }
+
+        let mut value = Value::Text("a".to_string());
+        value.set("Text.0", "b".to_string()).unwrap();
+        let text: &String = value.get("Text.0").unwrap();
+        assert_eq!(text, "b");
+        assert_eq!(
+            value.getenum("Number.0"),
+            Err(FieldAccessError::FieldNotInVariant {
+                variant: "Text",
+                field: "Number.0".to_string(),
+            })
+        );
+
+        let mut value = Value::Number(1);
+        *value.get_path_mut::<i64>("Number.0").unwrap() += 1;
+        assert_eq!(value.getenum("Number.0").unwrap(), ValueFieldEnum::Number_0(2));
+        assert_eq!(value.getstructinfo().field_types, vec!["String", "i64"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_field_attributes {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+    use std::marker::PhantomData;
+
+    #[derive(FieldAccessor)]
+    pub struct Dog {
+        name: String,
+        #[field_accessor(rename = "lifeExpectancy")]
+        life_expectancy: u32,
+        #[field_accessor(readonly)]
+        age: u32,
+        #[field_accessor(skip)]
+        secret: Vec<u8>,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Tagged<T> {
+        value: u32,
+        #[field_accessor(skip)]
+        marker: PhantomData<T>,
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut dog = Dog {
+            name: "Taro".to_string(),
+            life_expectancy: 9,
+            age: 3,
+            secret: vec![1],
+        };
+        dog.set("lifeExpectancy", 10u32).unwrap();
+        assert_eq!(dog.life_expectancy, 10);
+        let old_name: Result<&u32, FieldAccessError> = dog.get("life_expectancy");
+        assert!(matches!(old_name, Err(FieldAccessError::UnknownField { .. })));
+        assert_eq!(DogFields::lifeExpectancy.as_ref(), "lifeExpectancy");
+        assert_eq!(
+            dog.getenum("lifeExpectancy").unwrap(),
+            DogFieldEnum::lifeExpectancy(10)
+        );
+    }
+
+    #[test]
+    fn test_readonly() {
+        let mut dog = Dog {
+            name: "Taro".to_string(),
+            life_expectancy: 9,
+            age: 3,
+            secret: vec![1],
+        };
+        let age: &u32 = dog.get("age").unwrap();
+        assert_eq!(*age, 3);
+        assert_eq!(dog.getenum("age").unwrap(), DogFieldEnum::age(3));
+        let readonly = FieldAccessError::ReadOnlyField {
+            field: "age".to_string(),
+        };
+        assert_eq!(dog.set("age", 4u32), Err(readonly.clone()));
+        assert_eq!(dog.replace("age", 4u32), Err(readonly.clone()));
+        let taken: Result<u32, FieldAccessError> = dog.take("age");
+        assert_eq!(taken, Err(readonly.clone()));
+        let borrowed: Result<&mut u32, FieldAccessError> = dog.get_mut("age");
+        assert_eq!(borrowed, Err(readonly.clone()));
+        assert_eq!(dog.swap("lifeExpectancy", "age"), Err(readonly));
+        assert_eq!(dog.age, 3);
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut dog = Dog {
+            name: "Taro".to_string(),
+            life_expectancy: 9,
+            age: 3,
+            secret: vec![1],
+        };
+        assert!(matches!(
+            dog.getenum("secret"),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+        assert_eq!(dog.secret, vec![1]);
+        dog.set("name", "Jiro".to_string()).unwrap();
+        let info = dog.getstructinfo();
+        assert_eq!(info.field_names, vec!["name", "lifeExpectancy", "age"]);
+
+        let tagged: Tagged<String> = Tagged {
+            value: 1,
+            marker: PhantomData,
+        };
+        assert_eq!(tagged.getenum("value").unwrap(), TaggedFieldEnum::value(1));
+    }
+}
+
+#[cfg(test)]
+mod tests_nested_paths {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor, Clone, Debug, PartialEq, PartialOrd)]
+    pub struct UserData {
+        some_value: i32,
+        #[field_accessor(readonly)]
+        id: u64,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct User {
+        name: String,
+        #[field_accessor(nested)]
+        data: UserData,
+    }
+
+    #[test]
+    fn test_get_and_set_path() {
+        let mut user = User {
+            name: "Taro".to_string(),
+            data: UserData { some_value: 1, id: 7 },
+        };
+        assert_eq!(*user.get_path::<i32>("data.some_value").unwrap(), 1);
+        assert_eq!(user.get_path::<String>("name").unwrap(), "Taro");
+        user.set_path("data.some_value", 5i32).unwrap();
+        assert_eq!(user.data.some_value, 5);
+        *user.get_path_mut::<i32>("data.some_value").unwrap() += 1;
+        assert_eq!(user.data.some_value, 6);
+        assert_eq!(user.get_path::<UserData>("data").unwrap().id, 7);
+    }
+
+    #[test]
+    fn test_getenum_path() {
+        let user = User {
+            name: "Taro".to_string(),
+            data: UserData { some_value: 1, id: 7 },
+        };
+        assert_eq!(
+            user.getenum_path::<UserDataFieldEnum>("data.id").unwrap(),
+            UserDataFieldEnum::id(7)
+        );
+        assert_eq!(
+            user.getenum_path::<UserFieldEnum>("name").unwrap(),
+            UserFieldEnum::name("Taro".to_string())
+        );
+    }
+
+    #[test]
+    fn test_path_errors() {
+        let mut user = User {
+            name: "Taro".to_string(),
+            data: UserData { some_value: 1, id: 7 },
+        };
+        assert_eq!(
+            user.get_path::<i32>("data.bogus").unwrap_err(),
+            FieldAccessError::InvalidPath {
+                path: "data.bogus".to_string(),
+                segment: "bogus".to_string(),
+                source: Box::new(FieldAccessError::UnknownField {
+                    struct_name: "UserData",
+                    field: "bogus".to_string(),
+                }),
+            }
+        );
+        assert_eq!(
+            user.get_path::<i32>("name.len").unwrap_err(),
+            FieldAccessError::InvalidPath {
+                path: "name.len".to_string(),
+                segment: "name".to_string(),
+                source: Box::new(FieldAccessError::NotNested {
+                    field: "name".to_string(),
+                }),
+            }
+        );
+        assert!(matches!(
+            user.get_path::<u32>("data.some_value"),
+            Err(FieldAccessError::TypeMismatch { .. })
+        ));
+        assert_eq!(
+            user.set_path("data.id", 8u64).unwrap_err(),
+            FieldAccessError::InvalidPath {
+                path: "data.id".to_string(),
+                segment: "id".to_string(),
+                source: Box::new(FieldAccessError::ReadOnlyField {
+                    field: "id".to_string(),
+                }),
+            }
+        );
+        let error = user.get_path::<i32>("data.bogus").unwrap_err();
+        assert_eq!(error.to_string(), "invalid path 'data.bogus' at 'bogus'");
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "no field named 'bogus' in 'UserData'"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_indexed_paths {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(FieldAccessor, Clone, Debug, PartialEq)]
+    pub struct Team {
+        friends: Vec<String>,
+        scores: HashMap<String, u32>,
+        ranks: BTreeMap<String, u8>,
+        grid: [i32; 3],
+        #[field_accessor(readonly)]
+        frozen: Vec<u8>,
+    }
+
+    #[derive(FieldAccessor, Clone, Debug, PartialEq, PartialOrd)]
+    pub struct Roster {
+        friends: Vec<String>,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct League {
+        #[field_accessor(nested)]
+        roster: Roster,
+    }
+
+    #[test]
+    fn test_index_access() {
+        let mut team = Team {
+            friends: vec!["Taro".to_string(), "Jiro".to_string()],
+            scores: HashMap::from([("alice".to_string(), 10), ("a.b\"c".to_string(), 1)]),
+            ranks: BTreeMap::from([("bob".to_string(), 2)]),
+            grid: [1, 2, 3],
+            frozen: vec![0],
+        };
+        assert_eq!(team.get_path::<String>("friends[1]").unwrap(), "Jiro");
+        assert_eq!(*team.get_path::<u32>("scores[\"alice\"]").unwrap(), 10);
+        assert_eq!(*team.get_path::<u32>(r#"scores["a.b\"c"]"#).unwrap(), 1);
+        assert_eq!(*team.get_path::<u8>("ranks[\"bob\"]").unwrap(), 2);
+        team.set_path("grid[2]", 30i32).unwrap();
+        assert_eq!(team.grid, [1, 2, 30]);
+        *team.get_path_mut::<u32>("scores[\"alice\"]").unwrap() += 1;
+        assert_eq!(team.scores["alice"], 11);
+        assert_eq!(team.take_path::<String>("friends[0]").unwrap(), "Taro");
+        assert_eq!(team.friends, vec!["".to_string(), "Jiro".to_string()]);
+
+        let mut league = League { roster: Roster { friends: team.friends.clone() } };
+        league.set_path("roster.friends[1]", "Saburo".to_string()).unwrap();
+        assert_eq!(league.roster.friends[1], "Saburo");
+        assert_eq!(team.getenum("scores").unwrap(), TeamFieldEnum::scores(team.scores.clone()));
+    }
+
+    #[test]
+    fn test_index_through_accessors() {
+        let mut team = Team {
+            friends: vec!["Taro".to_string(), "Jiro".to_string()],
+            scores: HashMap::from([("alice".to_string(), 10), ("a.b\"c".to_string(), 1)]),
+            ranks: BTreeMap::from([("bob".to_string(), 2)]),
+            grid: [1, 2, 3],
+            frozen: vec![0],
+        };
+        let friend: &String = team.get("friends[1]").unwrap();
+        assert_eq!(friend, "Jiro");
+        let score: &mut u32 = team.get_mut("scores[\"alice\"]").unwrap();
+        *score += 5;
+        assert_eq!(team.scores["alice"], 15);
+        team.set("grid[0]", 10i32).unwrap();
+        assert_eq!(team.grid, [10, 2, 3]);
+        assert_eq!(team.replace("ranks[\"bob\"]", 1u8).unwrap(), 2);
+        let taken: String = team.take("friends[0]").unwrap();
+        assert_eq!(taken, "Taro");
+        assert_eq!(team.friends, vec!["".to_string(), "Jiro".to_string()]);
+        assert!(matches!(
+            team.set("friends[5]", "Shiro".to_string()),
+            Err(FieldAccessError::InvalidPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_index_errors() {
+        let mut team = Team {
+            friends: vec!["Taro".to_string(), "Jiro".to_string()],
+            scores: HashMap::from([("alice".to_string(), 10), ("a.b\"c".to_string(), 1)]),
+            ranks: BTreeMap::from([("bob".to_string(), 2)]),
+            grid: [1, 2, 3],
+            frozen: vec![0],
+        };
+        // For single-segment paths the failing segment is the whole path.
+        let err = |path: &str, source| FieldAccessError::InvalidPath {
+            path: path.to_string(),
+            segment: path.to_string(),
+            source: Box::new(source),
+        };
+        assert_eq!(
+            team.get_path::<String>("friends[5]").unwrap_err(),
+            err("friends[5]", FieldAccessError::OutOfRange { field: "friends".to_string(), index: 5, len: 2 })
+        );
+        assert_eq!(
+            team.get_path::<u32>("scores[\"carol\"]").unwrap_err(),
+            err("scores[\"carol\"]", FieldAccessError::MissingKey { field: "scores".to_string(), key: "carol".to_string() })
+        );
+        assert_eq!(
+            team.get_path::<u32>("scores[0]").unwrap_err(),
+            err("scores[0]", FieldAccessError::NotIndexable { field: "scores".to_string(), index: "[0]".to_string() })
+        );
+        assert_eq!(
+            team.set_path("frozen[0]", 1u8).unwrap_err(),
+            err("frozen[0]", FieldAccessError::ReadOnlyField { field: "frozen".to_string() })
+        );
+        assert!(matches!(
+            team.get_path::<u32>("friends[0]"),
+            Err(FieldAccessError::TypeMismatch { .. })
+        ));
+        #[derive(FieldAccessor)]
+        struct Lookup {
+            by_id: HashMap<u32, String>,
+        }
+        let mut lookup = Lookup { by_id: HashMap::from([(1, "one".to_string())]) };
+        assert_eq!(
+            lookup.get_path::<String>("by_id[1]").unwrap_err(),
+            err("by_id[1]", FieldAccessError::NotIndexable { field: "by_id".to_string(), index: "[1]".to_string() })
+        );
+        assert_eq!(
+            lookup.get_path_mut::<String>("by_id[\"1\"]").unwrap_err(),
+            err("by_id[\"1\"]", FieldAccessError::NotIndexable { field: "by_id".to_string(), index: "[\"1\"]".to_string() })
+        );
+        assert_eq!(lookup.get_path::<HashMap<u32, String>>("by_id").unwrap().len(), 1);
+        for path in ["friends[", "friends[x]", "friends[0]x", "scores[\"alice]", "friends.", ""] {
+            assert_eq!(
+                team.get_path::<String>(path).unwrap_err(),
+                FieldAccessError::MalformedPath { path: path.to_string() }
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_borrowed_enums {
+    use field_accessor::{FieldAccessError, FieldAccessor, IntoEnumIterator};
+
+    #[derive(Debug, PartialEq)]
+    pub struct Handle(u32);
+
+    #[derive(FieldAccessor)]
+    pub struct Dog {
+        name: String,
+        age: u32,
+        #[field_accessor(readonly)]
+        handle: Handle,
+    }
+
+    #[test]
+    fn test_getenum_ref() {
+        let dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            handle: Handle(1),
+        };
+        assert_eq!(
+            dog.getenum_ref("name").unwrap(),
+            DogFieldRef::name(&"Taro".to_string())
+        );
+        assert_eq!(dog.getenum_ref("handle").unwrap(), DogFieldRef::handle(&Handle(1)));
+        let values: Vec<DogFieldRef> = DogFields::iter()
+            .map(|field| dog.getenum_ref(field.as_ref()).unwrap())
+            .collect();
+        assert_eq!(values[1], DogFieldRef::age(&3));
+        assert!(matches!(
+            dog.getenum_ref("bogus"),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+    }
+
+    #[test]
+    fn test_getenum_mut() {
+        let mut dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            handle: Handle(1),
+        };
+        match dog.getenum_mut("name").unwrap() {
+            DogFieldMut::name(name) => name.push_str("mi"),
+            other => panic!("unexpected field {:?}", other),
+        }
+        if let Ok(DogFieldMut::age(age)) = dog.getenum_mut("age") {
+            *age += 1;
+        }
+        assert_eq!(dog.name, "Taromi");
+        assert_eq!(dog.age, 4);
+        assert_eq!(
+            dog.getenum_mut("handle").unwrap_err(),
+            FieldAccessError::ReadOnlyField {
+                field: "handle".to_string()
+            }
+        );
+        let age: &u32 = dog.get("age").unwrap();
+        assert_eq!(*age, 4);
+    }
+}
+
+#[cfg(test)]
+mod tests_owned_fields {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    pub struct Dog {
+        name: String,
+        #[field_accessor(readonly)]
+        age: u32,
+        #[field_accessor(skip)]
+        visits: Vec<u8>,
+    }
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    pub enum Shape {
+        Circle { radius: f64 },
+        Rect { width: f64, height: f64 },
+    }
+
+    #[test]
+    fn test_setenum() {
+        let mut dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            visits: vec![1],
+        };
+        dog.setenum(DogFieldEnum::name("Jiro".to_string())).unwrap();
+        assert_eq!(dog.name, "Jiro");
+        assert_eq!(
+            dog.setenum(DogFieldEnum::age(4)),
+            Err(FieldAccessError::ReadOnlyField {
+                field: "age".to_string()
+            })
+        );
+
+        let mut other = Dog {
+            name: "Ken".to_string(),
+            age: 5,
+            visits: vec![],
+        };
+        other.setenum(dog.getenum("name").unwrap()).unwrap();
+        assert_eq!(other.name, "Jiro");
+
+        let mut shape = Shape::Circle { radius: 1.0 };
+        shape.setenum(ShapeFieldEnum::radius(2.0)).unwrap();
+        assert_eq!(shape, Shape::Circle { radius: 2.0 });
+        assert_eq!(
+            shape.setenum(ShapeFieldEnum::width(2.0)),
+            Err(FieldAccessError::FieldNotInVariant {
+                variant: "Circle",
+                field: "width".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let fields = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            visits: vec![1],
+        }
+        .into_fields();
+        assert_eq!(
+            fields,
+            vec![DogFieldEnum::name("Taro".to_string()), DogFieldEnum::age(3)]
+        );
+        let rebuilt = Dog::try_from_fields(fields.into_iter().rev()).unwrap();
+        assert_eq!(
+            rebuilt,
+            Dog {
+                name: "Taro".to_string(),
+                age: 3,
+                visits: vec![],
+            }
+        );
+        assert_eq!(
+            Shape::Rect { width: 1.0, height: 2.0 }.into_fields(),
+            vec![ShapeFieldEnum::width(1.0), ShapeFieldEnum::height(2.0)]
+        );
+    }
+
+    #[test]
+    fn test_try_from_fields_errors() {
+        assert_eq!(
+            Dog::try_from_fields(vec![DogFieldEnum::name("Taro".to_string())]),
+            Err(FieldAccessError::MissingField {
+                struct_name: "Dog",
+                field: "age".to_string()
+            })
+        );
+        assert_eq!(
+            Dog::try_from_fields(vec![
+                DogFieldEnum::age(1),
+                DogFieldEnum::name("Taro".to_string()),
+                DogFieldEnum::age(2),
+            ]),
+            Err(FieldAccessError::DuplicateField {
+                field: "age".to_string()
+            })
+        );
+    }
+}
+
+// Public, so that the derived types are reachable from outside the crate and a field type
+// that is not is reported if it leaks.
+#[cfg(test)]
+pub mod tests_reflect {
+    use field_accessor::{FieldAccessor, IntoEnumIterator, Reflect};
+    use std::fmt::Debug;
+
+    #[derive(FieldAccessor)]
+    pub struct Dog {
+        name: String,
+        age: u32,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Wrapper<T> {
+        inner: T,
+    }
+
+    #[derive(FieldAccessor)]
+    pub enum Event {
+        Click { x: i32 },
+        Key { code: u32 },
+    }
+
+    #[derive(Debug, Clone, PartialEq, PartialOrd)]
+    struct Secret(u8);
+
+    #[derive(FieldAccessor)]
+    pub struct Vault {
+        secret: Secret,
+    }
+
+    fn dump<S: Reflect>(s: &S) -> Vec<String>
+    where
+        S::FieldEnum: Debug,
+    {
+        S::field_names()
+            .iter()
+            .filter_map(|name| s.getenum(name).ok())
+            .map(|value| format!("{:?}", value))
+            .collect()
+    }
+
+    fn copy_field<S: Reflect>(from: &S, to: &mut S, field: &str) {
+        to.setenum(from.getenum(field).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_generic_over_derived_types() {
+        let dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+        };
+        assert_eq!(dump(&dog), vec!["name(\"Taro\")", "age(3)"]);
+        assert_eq!(dump(&Wrapper { inner: 1.5f64 }), vec!["inner(1.5)"]);
+        assert_eq!(dump(&Event::Key { code: 7 }), vec!["code(7)"]);
+        assert_eq!(dump(&Event::Click { x: 1 }), vec!["x(1)"]);
+
+        let mut other = Dog {
+            name: "Jiro".to_string(),
+            age: 5,
+        };
+        copy_field(&dog, &mut other, "age");
+        assert_eq!(other.age, 3);
+    }
+
+    #[test]
+    fn test_associated_items() {
+        assert_eq!(<Dog as Reflect>::struct_name(), "Dog");
+        assert_eq!(<Event as Reflect>::field_names(), ["x", "code"]);
+        assert_eq!(<Dog as Reflect>::field_types().len(), 2);
+        assert_eq!(<Vault as Reflect>::field_names(), ["secret"]);
+        let fields: Vec<<Dog as Reflect>::Fields> = <Dog as Reflect>::Fields::iter().collect();
+        assert_eq!(fields, vec![DogFields::name, DogFields::age]);
+        assert_eq!("age".parse::<<Dog as Reflect>::Fields>(), Ok(DogFields::age));
+    }
+}
+
+#[cfg(test)]
+mod tests_dyn_access {
+    use field_accessor::{DynFieldAccess, FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor)]
+    pub struct Dog {
+        name: String,
+        #[field_accessor(readonly)]
+        age: u32,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Wrapper<T> {
+        inner: T,
+    }
+
+    #[derive(FieldAccessor)]
+    pub enum Event {
+        Click { x: i32, y: i32 },
+        Key { code: u32 },
+    }
+
+    #[test]
+    fn test_inspect() {
+        let registry: Vec<Box<dyn DynFieldAccess>> = vec![
+            Box::new(Dog {
+                name: "Taro".to_string(),
+                age: 3,
+            }),
+            Box::new(Wrapper { inner: 1u8 }),
+            Box::new(Event::Click { x: 1, y: 2 }),
+        ];
+        let names: Vec<_> = registry.iter().map(|item| item.type_name()).collect();
+        assert_eq!(names, vec!["Dog", "Wrapper", "Event"]);
+        assert_eq!(registry[0].field_names(), ["name", "age"]);
+        assert_eq!(registry[2].field_names(), ["x", "y"]);
+        assert_eq!(Event::Key { code: 1 }.field_names(), ["code"]);
+        let age = registry[0].get_dyn("age").unwrap();
+        assert_eq!(age.downcast_ref::<u32>(), Some(&3));
+        assert!(matches!(
+            registry[2].get_dyn("code"),
+            Err(FieldAccessError::FieldNotInVariant { .. })
+        ));
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut registry: Vec<Box<dyn DynFieldAccess>> = vec![
+            Box::new(Dog {
+                name: "Taro".to_string(),
+                age: 3,
+            }),
+            Box::new(Wrapper { inner: 1u8 }),
+            Box::new(Event::Click { x: 1, y: 2 }),
+        ];
+        registry[0]
+            .set_dyn("name", Box::new("Jiro".to_string()))
+            .unwrap();
+        *registry[1]
+            .get_dyn_mut("inner")
+            .unwrap()
+            .downcast_mut::<u8>()
+            .unwrap() += 1;
+        assert_eq!(
+            registry[0].get_dyn("name").unwrap().downcast_ref::<String>(),
+            Some(&"Jiro".to_string())
+        );
+        assert_eq!(
+            registry[1].get_dyn("inner").unwrap().downcast_ref::<u8>(),
+            Some(&2)
+        );
+        assert_eq!(
+            registry[2].set_dyn("x", Box::new(1u32)),
+            Err(FieldAccessError::TypeMismatch {
+                field: "x".to_string(),
+                expected: "u32",
+                found: "i32",
+            })
+        );
+        assert_eq!(
+            registry[2].set_dyn("x", Box::new(1u64)),
+            Err(FieldAccessError::TypeMismatch {
+                field: "x".to_string(),
+                expected: "dyn Any",
+                found: "i32",
+            })
+        );
+        assert_eq!(
+            registry[0].set_dyn("age", Box::new(4u32)),
+            Err(FieldAccessError::ReadOnlyField {
+                field: "age".to_string()
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_set_from_str {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+    use std::net::Ipv4Addr;
+
+    #[derive(FieldAccessor)]
+    pub struct Config {
+        name: String,
+        age: u32,
+        ratio: f64,
+        host: Ipv4Addr,
+        #[field_accessor(readonly)]
+        id: u64,
+        #[field_accessor(no_parse)]
+        tags: Vec<String>,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Wrapper<T> {
+        inner: T,
+    }
+
+    #[test]
+    fn test_set_from_str() {
+        let mut config = Config {
+            name: "Taro".to_string(),
+            age: 3,
+            ratio: 0.5,
+            host: Ipv4Addr::LOCALHOST,
+            id: 1,
+            tags: vec![],
+        };
+        for line in ["age=4", "name=Jiro", "ratio=1.5", "host=10.0.0.1"] {
+            let (field, value) = line.split_once('=').unwrap();
+            config.set_from_str(field, value).unwrap();
+        }
+        assert_eq!(config.age, 4);
+        assert_eq!(config.name, "Jiro");
+        assert_eq!(config.ratio, 1.5);
+        assert_eq!(config.host, Ipv4Addr::new(10, 0, 0, 1));
+
+        let mut wrapper = Wrapper { inner: 0i8 };
+        wrapper.set_from_str("inner", "-3").unwrap();
+        assert_eq!(wrapper.inner, -3);
+    }
+
+    #[test]
+    fn test_set_from_str_errors() {
+        let mut config = Config {
+            name: "Taro".to_string(),
+            age: 3,
+            ratio: 0.5,
+            host: Ipv4Addr::LOCALHOST,
+            id: 1,
+            tags: vec![],
+        };
+        assert_eq!(
+            config.set_from_str("age", "four"),
+            Err(FieldAccessError::ParseFailed {
+                field: "age".to_string(),
+                expected: "u32",
+                message: "invalid digit found in string".to_string(),
+            })
+        );
+        assert_eq!(
+            config.set_from_str("age", "four").unwrap_err().to_string(),
+            "cannot parse a 'u32' for field 'age': invalid digit found in string"
+        );
+        assert_eq!(
+            config.set_from_str("tags", "a,b"),
+            Err(FieldAccessError::NotParsable {
+                field: "tags".to_string()
+            })
+        );
+        assert_eq!(
+            config.set_from_str("id", "2"),
+            Err(FieldAccessError::ReadOnlyField {
+                field: "id".to_string()
+            })
+        );
+        assert!(matches!(
+            config.set_from_str("bogus", "1"),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+        assert_eq!(config.age, 3);
+    }
+}
+
+#[cfg(test)]
+mod tests_get_string {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+    use std::collections::BTreeMap;
+
+    #[derive(FieldAccessor)]
+    pub struct Dog {
+        name: String,
+        age: u32,
+        #[field_accessor(debug_fmt)]
+        tags: Vec<&'static str>,
+        #[field_accessor(skip)]
+        secret: Vec<u8>,
+    }
+
+    #[derive(FieldAccessor)]
+    pub enum Event {
+        Click { x: i32, y: i32 },
+        Key { code: char },
+    }
+
+    #[test]
+    fn test_get_string() {
+        let dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            tags: vec!["good"],
+            secret: vec![],
+        };
+        assert_eq!(dog.get_string("name").unwrap(), "Taro");
+        assert_eq!(dog.get_string("age").unwrap(), "3");
+        assert_eq!(dog.get_string("tags").unwrap(), "[\"good\"]");
+        assert!(matches!(
+            dog.get_string("secret"),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+        assert!(dog.secret.is_empty());
+    }
+
+    #[test]
+    fn test_to_string_map() {
+        let expected: BTreeMap<String, String> = [("name", "Taro"), ("age", "3"), ("tags", "[\"good\"]")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let dog = Dog {
+            name: "Taro".to_string(),
+            age: 3,
+            tags: vec!["good"],
+            secret: vec![],
+        };
+        assert_eq!(dog.to_string_map(), expected);
+
+        let key = Event::Key { code: 'q' };
+        assert_eq!(key.to_string_map(), BTreeMap::from([("code".to_string(), "q".to_string())]));
+        assert_eq!(Event::Click { x: 1, y: 2 }.to_string_map().len(), 2);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg(test)]
+mod tests_serde {
+    use field_accessor::{FieldAccessError, FieldAccessor, PatchOperation};
+    use serde_json::json;
+
+    #[derive(FieldAccessor)]
+    pub struct User {
+        name: String,
+        age: u32,
+        #[field_accessor(rename = "e-mail")]
+        email: Option<String>,
+        #[field_accessor(readonly)]
+        id: u64,
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Wrapper<T> {
+        value: T,
+    }
+
+    #[test]
+    fn test_get_json() {
+        let user = User {
+            name: "Taro".to_string(),
+            age: 30,
+            email: None,
+            id: 1,
+        };
+        assert_eq!(user.get_json("name").unwrap(), json!("Taro"));
+        assert_eq!(user.get_json("e-mail").unwrap(), json!(null));
+        assert_eq!(user.get_json("id").unwrap(), json!(1));
+        assert!(matches!(
+            user.get_json("email"),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_json_patch() {
+        let mut user = User {
+            name: "Taro".to_string(),
+            age: 30,
+            email: None,
+            id: 1,
+        };
+        let patch = json!({ "age": 31, "e-mail": "taro@example.com" });
+        for (field, value) in patch.as_object().unwrap() {
+            user.set_json(field, value.clone()).unwrap();
+        }
+        assert_eq!(user.age, 31);
+        assert_eq!(user.email.as_deref(), Some("taro@example.com"));
+
+        assert_eq!(
+            user.set_json("age", json!("old")),
+            Err(FieldAccessError::DeserializeFailed {
+                field: "age".to_string(),
+                expected: "u32",
+                message: "invalid type: string \"old\", expected u32".to_string(),
+            })
+        );
+        assert_eq!(user.age, 31);
+        assert!(matches!(
+            user.set_json("id", json!(2)),
+            Err(FieldAccessError::ReadOnlyField { .. })
+        ));
+
+        let mut wrapper = Wrapper { value: vec![1u8] };
+        wrapper.set_json("value", json!([1, 2])).unwrap();
+        assert_eq!(wrapper.get_json("value").unwrap(), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_serialize_enums() {
+        let user = User {
+            name: "Taro".to_string(),
+            age: 30,
+            email: None,
+            id: 1,
+        };
+        let value = serde_json::to_value(user.getenum("age").unwrap()).unwrap();
+        assert_eq!(value, json!({ "age": 30 }));
+        let email: UserFieldEnum = serde_json::from_value(json!({ "e-mail": "a@b.c" })).unwrap();
+        assert_eq!(email, UserFieldEnum::email(Some("a@b.c".to_string())));
+
+        assert_eq!(serde_json::to_value(UserFields::email).unwrap(), json!("e-mail"));
+        let field: UserFields = serde_json::from_value(json!("age")).unwrap();
+        assert_eq!(field, UserFields::age);
+    }
+
+    #[derive(FieldAccessor, Clone, serde::Serialize, serde::Deserialize)]
+    pub struct Address {
+        city: String,
+        zip: String,
+    }
+
+    #[derive(FieldAccessor, Clone)]
+    pub struct Profile {
+        name: String,
+        #[field_accessor(nested)]
+        address: Address,
+        tags: Vec<String>,
+        scores: std::collections::BTreeMap<String, u32>,
+        #[field_accessor(readonly)]
+        id: u64,
+    }
+
+    fn patch(value: serde_json::Value) -> Vec<PatchOperation> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_apply_json_patch() {
+        let mut profile = Profile {
+            name: "Taro".to_string(),
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: "100".to_string(),
+            },
+            tags: vec!["a".to_string(), "b".to_string()],
+            scores: [("math".to_string(), 80)].into(),
+            id: 1,
+        };
+        profile
+            .apply_json_patch(&patch(json!([
+                { "op": "test", "path": "/address/city", "value": "Tokyo" },
+                { "op": "replace", "path": "/address/city", "value": "Osaka" },
+                { "op": "add", "path": "/tags/1", "value": "x" },
+                { "op": "add", "path": "/tags/-", "value": "z" },
+                { "op": "remove", "path": "/tags/0" },
+                { "op": "add", "path": "/scores/art", "value": 70 },
+                { "op": "copy", "from": "/address/zip", "path": "/name" },
+                { "op": "move", "from": "/scores/math", "path": "/scores/music" },
+            ])))
+            .unwrap();
+        assert_eq!(profile.address.city, "Osaka");
+        assert_eq!(profile.tags, ["x", "b", "z"]);
+        assert_eq!(profile.name, "100");
+        assert_eq!(
+            profile.scores,
+            [("art".to_string(), 70), ("music".to_string(), 80)].into()
+        );
+    }
+
+    #[test]
+    fn test_apply_json_patch_is_atomic() {
+        let mut profile = Profile {
+            name: "Taro".to_string(),
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: "100".to_string(),
+            },
+            tags: vec!["a".to_string(), "b".to_string()],
+            scores: [("math".to_string(), 80)].into(),
+            id: 1,
+        };
+        let err = profile
+            .apply_json_patch(&patch(json!([
+                { "op": "replace", "path": "/name", "value": "Jiro" },
+                { "op": "replace", "path": "/tags/5", "value": "x" },
+            ])))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            FieldAccessError::PatchFailed {
+                index: 1,
+                source: Box::new(FieldAccessError::InvalidPath {
+                    path: "/tags/5".to_string(),
+                    segment: "5".to_string(),
+                    source: Box::new(FieldAccessError::OutOfRange {
+                        field: "tags".to_string(),
+                        index: 5,
+                        len: 2,
+                    }),
+                }),
+            }
+        );
+        assert_eq!(profile.name, "Taro");
+
+        // The patch applies to the JSON form, but the new name is not a string.
+        let err = profile
+            .apply_json_patch(&patch(json!([
+                { "op": "add", "path": "/tags/-", "value": "c" },
+                { "op": "replace", "path": "/name", "value": 5 },
+            ])))
+            .unwrap_err();
+        assert!(matches!(err, FieldAccessError::DeserializeFailed { ref field, .. } if field == "name"));
+        assert_eq!(profile.tags.len(), 2);
+
+        let test = patch(json!([{ "op": "test", "path": "/name", "value": "Jiro" }]));
+        assert!(matches!(
+            profile.apply_json_patch(&test),
+            Err(FieldAccessError::PatchFailed { index: 0, .. })
+        ));
+        for path in ["/id", "/name"] {
+            let remove = patch(json!([{ "op": "remove", "path": path }]));
+            assert!(profile.apply_json_patch(&remove).is_err());
+        }
+        for path in ["", "name", "/bogus", "/address/~2"] {
+            let replace = patch(json!([{ "op": "replace", "path": path, "value": 1 }]));
+            assert!(profile.apply_json_patch(&replace).is_err());
+        }
+        assert_eq!(profile.id, 1);
+    }
+
+    #[test]
+    fn test_diff_json_patch() {
+        let before = Profile {
+            name: "Taro".to_string(),
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: "100".to_string(),
+            },
+            tags: vec!["a".to_string(), "b".to_string()],
+            scores: [("math".to_string(), 80)].into(),
+            id: 1,
+        };
+        let mut after = Profile {
+            name: "Taro".to_string(),
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: "100".to_string(),
+            },
+            tags: vec!["a".to_string(), "b".to_string()],
+            scores: [("math".to_string(), 80)].into(),
+            id: 1,
+        };
+        after.address.zip = "530".to_string();
+        after.tags.pop();
+        after.scores.insert("a/b".to_string(), 1);
+        let diff = before.diff_json_patch(&after).unwrap();
+        assert_eq!(
+            diff,
+            patch(json!([
+                { "op": "replace", "path": "/address/zip", "value": "530" },
+                { "op": "add", "path": "/scores/a~1b", "value": 1 },
+                { "op": "remove", "path": "/tags/1" },
+            ]))
+        );
+        let mut patched = before.clone();
+        patched.apply_json_patch(&diff).unwrap();
+        assert!(patched.diff_json_patch(&after).unwrap().is_empty());
+        assert_eq!(before.to_json_map().unwrap()["id"], json!(1));
+    }
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    pub enum Shape {
+        Circle { r: f64 },
+        Rect { w: f64, h: f64 },
+    }
+
+    #[test]
+    fn test_diff_json_patch_of_enums() {
+        let before = Shape::Rect { w: 2.0, h: 1.0 };
+        let after = Shape::Rect { w: 3.0, h: 1.0 };
+        let mut patched = Shape::Rect { w: 2.0, h: 1.0 };
+        patched
+            .apply_json_patch(&before.diff_json_patch(&after).unwrap())
+            .unwrap();
+        assert_eq!(patched, after);
+
+        assert_eq!(
+            before.diff_json_patch(&Shape::Circle { r: 1.0 }),
+            Err(FieldAccessError::VariantMismatch {
+                variant: "Rect",
+                found: "Circle",
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_diff {
+    use field_accessor::{FieldAccessor, FieldChange};
+
+    #[derive(FieldAccessor, Clone, PartialEq, Debug)]
+    pub struct Address {
+        city: String,
+        zip: u32,
+    }
+
+    #[derive(FieldAccessor, Clone)]
+    pub struct User {
+        name: String,
+        #[field_accessor(rename = "years")]
+        age: u32,
+        #[field_accessor(nested)]
+        address: Address,
+        #[field_accessor(skip)]
+        cache: Vec<u8>,
+    }
+
+    #[derive(FieldAccessor)]
+    pub enum Shape {
+        Circle { r: f64 },
+        Rect { w: f64, h: f64 },
+        Square { w: f64 },
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = User {
+            name: "Taro".to_string(),
+            age: 30,
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: 100,
+            },
+            cache: vec![],
+        };
+        let mut new = User {
+            name: "Taro".to_string(),
+            age: 30,
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: 100,
+            },
+            cache: vec![],
+        };
+        assert!(old.diff(&new).is_empty());
+
+        new.age = 31;
+        new.address.zip = 530;
+        new.cache.push(1);
+        assert_eq!(
+            old.diff(&new),
+            [
+                FieldChange {
+                    field: "years".to_string(),
+                    old: Some(UserFieldRef::years(&30)),
+                    new: Some(UserFieldRef::years(&31)),
+                },
+                FieldChange {
+                    field: "address".to_string(),
+                    old: Some(UserFieldRef::address(&old.address)),
+                    new: Some(UserFieldRef::address(&new.address)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_variants() {
+        let rect = Shape::Rect { w: 1.0, h: 2.0 };
+        assert!(rect.diff(&Shape::Rect { w: 1.0, h: 2.0 }).is_empty());
+        let changes = rect.diff(&Shape::Square { w: 3.0 });
+        assert_eq!(
+            changes,
+            [
+                FieldChange {
+                    field: "w".to_string(),
+                    old: Some(ShapeFieldRef::w(&1.0)),
+                    new: Some(ShapeFieldRef::w(&3.0)),
+                },
+                FieldChange {
+                    field: "h".to_string(),
+                    old: Some(ShapeFieldRef::h(&2.0)),
+                    new: None,
+                },
+            ]
+        );
+
+        // Fields that only one side has are reported on that side.
+        let circle = Shape::Circle { r: 1.0 };
+        let fields: Vec<_> = rect
+            .diff(&circle)
+            .into_iter()
+            .map(|change| (change.field, change.old.is_some(), change.new.is_some()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("r".to_string(), false, true),
+                ("w".to_string(), true, false),
+                ("h".to_string(), true, false),
+            ]
+        );
+        let changes = rect.diff_nested(&circle);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].new.unwrap().downcast_ref::<f64>(), Some(&1.0));
+        assert_eq!(changes[0].downcast::<f64>(), None);
+    }
+
+    #[test]
+    fn test_diff_nested() {
+        let old = User {
+            name: "Taro".to_string(),
+            age: 30,
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: 100,
+            },
+            cache: vec![],
+        };
+        let mut new = User {
+            name: "Taro".to_string(),
+            age: 30,
+            address: Address {
+                city: "Tokyo".to_string(),
+                zip: 100,
+            },
+            cache: vec![],
+        };
+        new.name = "Jiro".to_string();
+        new.address.zip = 530;
+        let changes = old.diff_nested(&new);
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["name", "address.zip"]);
+        assert_eq!(changes[1].downcast::<u32>(), Some((&100, &530)));
+        assert_eq!(changes[1].downcast::<String>(), None);
+    }
+}
+
+#[cfg(test)]
+mod tests_track_changes {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor)]
+    #[field_accessor(track_changes)]
+    pub struct Row {
+        id: u64,
+        name: String,
+        #[field_accessor(rename = "e-mail")]
+        email: String,
+        #[field_accessor(readonly)]
+        created: u64,
+    }
+
+    #[derive(FieldAccessor)]
+    #[field_accessor(track_changes)]
+    pub struct Pair<T> {
+        left: T,
+        right: T,
+    }
+
+    #[test]
+    fn test_dirty_fields() {
+        let mut row = RowTracked::new(Row {
+            id: 1,
+            name: "Taro".to_string(),
+            email: "taro@example.com".to_string(),
+            created: 0,
+        });
+        assert!(row.dirty_fields().is_empty());
+        let name: &String = row.get("name").unwrap();
+        assert_eq!(name, "Taro");
+
+        row.set("e-mail", "jiro@example.com".to_string()).unwrap();
+        assert_eq!(row.dirty_fields(), ["e-mail"]);
+        assert!(row.is_dirty("e-mail"));
+        assert!(!row.is_dirty("name"));
+        assert!(!row.is_dirty("bogus"));
+
+        *row.get_mut::<u64, _>("id").unwrap() += 1;
+        let old: String = row.replace("name", "Jiro".to_string()).unwrap();
+        assert_eq!(old, "Taro");
+        assert_eq!(row.dirty_fields(), ["id", "name", "e-mail"]);
+        assert_eq!(row.id, 2);
+
+        row.clear_dirty();
+        assert!(row.dirty_fields().is_empty());
+        let taken: String = row.take("name").unwrap();
+        assert_eq!(taken, "Jiro");
+        row.swap("name", "e-mail").unwrap();
+        assert_eq!(row.dirty_fields(), ["name", "e-mail"]);
+        assert_eq!(row.into_inner().email, "");
+    }
+
+    #[test]
+    fn test_failed_changes_stay_clean() {
+        let mut row = RowTracked::new(Row {
+            id: 1,
+            name: "Taro".to_string(),
+            email: "taro@example.com".to_string(),
+            created: 0,
+        });
+        assert!(matches!(
+            row.set("created", 5u64),
+            Err(FieldAccessError::ReadOnlyField { .. })
+        ));
+        assert!(row.set("bogus", 5u64).is_err());
+        assert!(row.swap("id", "name").is_err());
+        assert!(row.dirty_fields().is_empty());
+
+        let mut pair = PairTracked::new(Pair { left: 1, right: 2 });
+        pair.swap("left", "right").unwrap();
+        assert_eq!((pair.left, pair.right), (2, 1));
+        assert_eq!(pair.dirty_fields(), ["left", "right"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_observe {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(FieldAccessor)]
+    #[field_accessor(observe)]
+    pub struct Settings {
+        volume: u8,
+        muted: bool,
+        #[field_accessor(rename = "device-name")]
+        device: String,
+    }
+
+    #[derive(FieldAccessor)]
+    #[field_accessor(observe, track_changes)]
+    pub struct Pair<T> {
+        left: T,
+        right: T,
+    }
+
+    #[test]
+    fn test_on_change() {
+        let mut settings = SettingsObserved::new(Settings {
+            volume: 5,
+            muted: false,
+            device: "speaker".to_string(),
+        });
+        let volume_log = Rc::new(RefCell::new(vec![]));
+        let all_log = Rc::new(RefCell::new(vec![]));
+        let log = volume_log.clone();
+        settings
+            .on_change(Some("volume"), move |field, old, new| {
+                log.borrow_mut().push((field.to_string(), old.clone(), new.clone()));
+            })
+            .unwrap();
+        let log = all_log.clone();
+        settings
+            .on_change(None, move |field, _, _| log.borrow_mut().push(field.to_string()))
+            .unwrap();
+
+        settings.set("volume", 7u8).unwrap();
+        settings.set("muted", true).unwrap();
+        let old: String = settings.replace("device-name", "headphones".to_string()).unwrap();
+        assert_eq!(old, "speaker");
+        assert_eq!(
+            *volume_log.borrow(),
+            [(
+                "volume".to_string(),
+                SettingsFieldEnum::volume(5),
+                SettingsFieldEnum::volume(7)
+            )]
+        );
+        assert_eq!(*all_log.borrow(), ["volume", "muted", "device-name"]);
+        assert_eq!(settings.volume, 7);
+
+        let taken: u8 = settings.take("volume").unwrap();
+        assert_eq!(taken, 7);
+        assert_eq!(volume_log.borrow()[1].2, SettingsFieldEnum::volume(0));
+        assert_eq!(settings.into_inner().device, "headphones");
+    }
+
+    #[test]
+    fn test_failed_changes_are_not_observed() {
+        let mut settings = SettingsObserved::new(Settings {
+            volume: 5,
+            muted: false,
+            device: "speaker".to_string(),
+        });
+        assert!(matches!(
+            settings.on_change(Some("device"), |_, _, _| {}),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+        let calls = Rc::new(RefCell::new(0));
+        let counter = calls.clone();
+        settings
+            .on_change(None, move |_, _, _| *counter.borrow_mut() += 1)
+            .unwrap();
+        assert!(settings.set("volume", true).is_err());
+        assert!(settings.swap("volume", "muted").is_err());
+        assert_eq!(*calls.borrow(), 0);
+
+        let mut pair = PairObserved::new(Pair { left: 1, right: 2 });
+        let swaps = Rc::new(RefCell::new(vec![]));
+        let log = swaps.clone();
+        pair.on_change(None, move |field, old, new| {
+            log.borrow_mut().push((field.to_string(), old.clone(), new.clone()));
+        })
+        .unwrap();
+        pair.swap("left", "right").unwrap();
+        assert_eq!(
+            *swaps.borrow(),
+            [
+                ("left".to_string(), PairFieldEnum::left(1), PairFieldEnum::left(2)),
+                ("right".to_string(), PairFieldEnum::right(2), PairFieldEnum::right(1)),
+            ]
+        );
+        assert!(PairTracked::new(pair.into_inner()).dirty_fields().is_empty());
+    }
+
+    #[test]
+    fn test_on_op() {
+        let mut settings = SettingsObserved::new(Settings {
+            volume: 5,
+            muted: false,
+            device: "speaker".to_string(),
+        });
+        let ops = Rc::new(RefCell::new(vec![]));
+        let log = ops.clone();
+        settings.on_op(move |op| log.borrow_mut().push(op.clone()));
+        settings.set("volume", 7u8).unwrap();
+        assert!(settings.set("muted", 1u8).is_err());
+        let _: String = settings.replace("device-name", "headphones".to_string()).unwrap();
+        let _: bool = settings.take("muted").unwrap();
+        assert_eq!(
+            *ops.borrow(),
+            [
+                SettingsOp::set(SettingsFieldEnum::volume(7)),
+                SettingsOp::set(SettingsFieldEnum::device("headphones".to_string())),
+                SettingsOp::take("muted"),
+            ]
+        );
+
+        let mut replayed = Settings {
+            volume: 5,
+            muted: true,
+            device: "speaker".to_string(),
+        };
+        replayed.apply_ops(ops.take()).unwrap();
+        let settings = settings.into_inner();
+        assert_eq!(
+            (replayed.volume, replayed.muted, replayed.device),
+            (settings.volume, settings.muted, settings.device)
+        );
+
+        let mut pair = PairObserved::new(Pair { left: 1, right: 2 });
+        let swaps = Rc::new(RefCell::new(vec![]));
+        let log = swaps.clone();
+        pair.on_op(move |op| log.borrow_mut().push(op.clone()));
+        pair.swap("left", "right").unwrap();
+        assert_eq!(*swaps.borrow(), [PairOp::swap("left", "right")]);
+    }
+}
+
+#[cfg(test)]
+mod tests_history {
+    use field_accessor::FieldAccessor;
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    #[field_accessor(history)]
+    pub struct Document {
+        title: String,
+        width: u32,
+        height: u32,
+        #[field_accessor(readonly)]
+        id: u32,
+    }
+
+    #[test]
+    fn test_undo_indexed_change() {
+        #[derive(FieldAccessor)]
+        #[field_accessor(history)]
+        struct Playlist {
+            songs: Vec<String>,
+        }
+
+        let mut playlist = PlaylistHistory::new(Playlist {
+            songs: vec!["a".to_string(), "b".to_string()],
+        });
+        playlist.set("songs[1]", "c".to_string()).unwrap();
+        assert_eq!(playlist.songs, vec!["a".to_string(), "c".to_string()]);
+        assert!(playlist.undo());
+        assert_eq!(playlist.songs, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut doc = DocumentHistory::new(Document {
+            title: "draft".to_string(),
+            width: 640,
+            height: 480,
+            id: 1,
+        });
+        assert!(!doc.can_undo());
+        doc.set("title", "final".to_string()).unwrap();
+        doc.swap("width", "height").unwrap();
+        let old: u32 = doc.replace("width", 1024).unwrap();
+        assert_eq!(old, 480);
+        assert_eq!((doc.width, doc.height), (1024, 640));
+
+        assert!(doc.undo());
+        assert_eq!((doc.width, doc.height), (480, 640));
+        assert!(doc.undo());
+        assert_eq!((doc.width, doc.height), (640, 480));
+        assert!(doc.can_redo());
+        assert!(doc.redo());
+        assert_eq!((doc.width, doc.height), (480, 640));
+        assert!(doc.undo());
+        assert!(doc.undo());
+        assert_eq!(doc.title, "draft");
+        assert!(!doc.undo());
+
+        // A new change discards the undone steps.
+        assert!(doc.redo());
+        doc.set("height", 100u32).unwrap();
+        assert!(!doc.can_redo());
+    }
+
+    #[test]
+    fn test_failed_changes_are_not_recorded() {
+        let mut doc = DocumentHistory::new(Document {
+            title: "draft".to_string(),
+            width: 640,
+            height: 480,
+            id: 1,
+        });
+        assert!(doc.set("id", 2u32).is_err());
+        assert!(doc.set("missing", 2u32).is_err());
+        assert!(doc.swap("title", "width").is_err());
+        assert!(!doc.can_undo());
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut doc = DocumentHistory::new(Document {
+            title: "draft".to_string(),
+            width: 640,
+            height: 480,
+            id: 1,
+        });
+        doc.begin_group();
+        doc.set("width", 800u32).unwrap();
+        doc.set("height", 600u32).unwrap();
+        let _: String = doc.take("title").unwrap();
+        doc.end_group();
+        doc.set("width", 1u32).unwrap();
+
+        assert!(doc.undo());
+        assert_eq!(doc.width, 800);
+        assert!(doc.undo());
+        assert_eq!((doc.width, doc.height), (640, 480));
+        assert_eq!(doc.title, "draft");
+        assert!(!doc.undo());
+        assert!(doc.redo());
+        assert_eq!(doc.title, "");
+
+        // An open group is ended by `undo`.
+        doc.begin_group();
+        doc.set("width", 2u32).unwrap();
+        assert!(doc.undo());
+        assert_eq!(doc.width, 800);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut doc = DocumentHistory::with_capacity(
+            Document {
+                title: "draft".to_string(),
+                width: 640,
+                height: 480,
+                id: 1,
+            },
+            2,
+        );
+        for width in 1..=3u32 {
+            doc.set("width", width).unwrap();
+        }
+        assert!(doc.undo());
+        assert!(doc.undo());
+        assert!(!doc.undo());
+        assert_eq!(doc.width, 1);
+        doc.clear_history();
+        assert!(!doc.can_redo());
+        assert_eq!(doc.into_inner().width, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests_validation {
+    use field_accessor::{DynFieldAccess, FieldAccessError, FieldAccessor};
+
+    fn no_spaces(value: &str) -> Result<(), String> {
+        match value.contains(' ') {
+            true => Err("must not contain spaces".to_string()),
+            false => Ok(()),
+        }
+    }
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    pub struct Person {
+        #[field_accessor(min_len = 1, max_len = 8, validate = "no_spaces")]
+        name: String,
+        #[field_accessor(range(min = 0, max = 150))]
+        age: i32,
+        #[field_accessor(range(min = "-273.15"))]
+        temperature: f64,
+        #[field_accessor(no_parse)]
+        tags: Vec<String>,
+    }
+
+    #[derive(FieldAccessor)]
+    pub enum Reading {
+        Celsius {
+            #[field_accessor(range(max = 100))]
+            value: i32,
+        },
+        Raw(#[field_accessor(min_len = 2)] Vec<u8>),
+    }
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    pub struct Range {
+        #[field_accessor(range(max = 10))]
+        low: i32,
+        high: i32,
+    }
+
+    fn invalid(field: &str, reason: &str) -> FieldAccessError {
+        FieldAccessError::ValidationFailed {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_set_and_replace() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            temperature: 36.5,
+            tags: vec![],
+        };
+        person.set("age", 31).unwrap();
+        assert_eq!(person.set("age", 151), Err(invalid("age", "must be at most 150")));
+        assert_eq!(person.set("age", -1), Err(invalid("age", "must be at least 0")));
+        assert_eq!(
+            person.replace("name", "Ta ro".to_string()),
+            Err(invalid("name", "must not contain spaces"))
+        );
+        assert_eq!(
+            person.set("name", String::new()),
+            Err(invalid("name", "must have a length of at least 1"))
+        );
+        assert_eq!(
+            person.set("name", "Bartholomew".to_string()),
+            Err(invalid("name", "must have a length of at most 8"))
+        );
+        assert_eq!(
+            person.set("temperature", -300.0),
+            Err(invalid("temperature", "must be at least -273.15"))
+        );
+        assert_eq!(person.age, 31);
+        assert_eq!(person.name, "Taro");
+        person.set("tags", vec!["x".to_string()]).unwrap();
+    }
+
+    #[test]
+    fn test_other_writes() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            temperature: 36.5,
+            tags: vec![],
+        };
+        assert_eq!(
+            person.setenum(PersonFieldEnum::age(200)),
+            Err(invalid("age", "must be at most 150"))
+        );
+        assert_eq!(
+            person.set_from_str("age", "-5"),
+            Err(invalid("age", "must be at least 0"))
+        );
+        assert!(matches!(
+            person.set_dyn("age", Box::new(999)),
+            Err(FieldAccessError::ValidationFailed { .. })
+        ));
+        assert_eq!(
+            person,
+            Person {
+                name: "Taro".to_string(),
+                age: 30,
+                temperature: 36.5,
+                tags: vec![],
+            }
+        );
+
+        let fields = vec![
+            PersonFieldEnum::name("A B".to_string()),
+            PersonFieldEnum::age(1),
+            PersonFieldEnum::temperature(0.0),
+            PersonFieldEnum::tags(vec![]),
+        ];
+        assert_eq!(
+            Person::try_from_fields(fields),
+            Err(invalid("name", "must not contain spaces"))
+        );
+
+        let mut reading = Reading::Celsius { value: 20 };
+        assert_eq!(reading.set("value", 101), Err(invalid("value", "must be at most 100")));
+        let mut raw = Reading::Raw(vec![1, 2]);
+        assert_eq!(
+            raw.set("Raw.0", vec![1u8]),
+            Err(invalid("Raw.0", "must have a length of at least 2"))
+        );
+        assert!(reading.set("value", -40).is_ok());
+    }
+
+    #[test]
+    fn test_swap_and_take() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            temperature: 36.5,
+            tags: vec![],
+        };
+        assert_eq!(
+            person.take("name"),
+            Err::<String, _>(invalid("name", "must have a length of at least 1"))
+        );
+        assert_eq!(
+            person.apply_op(PersonOp::take("name")),
+            Err(invalid("name", "must have a length of at least 1"))
+        );
+        assert_eq!(person.name, "Taro");
+        assert_eq!(person.take("age"), Ok(30));
+
+        let mut range = Range { low: 5, high: 20 };
+        assert_eq!(range.swap("low", "high"), Err(invalid("low", "must be at most 10")));
+        assert_eq!(range, Range { low: 5, high: 20 });
+        range.high = 8;
+        range.swap("low", "high").unwrap();
+        assert_eq!(range, Range { low: 8, high: 5 });
+    }
+
+    #[test]
+    fn test_paths() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            temperature: 36.5,
+            tags: vec![],
+        };
+        assert_eq!(person.set_path("age", 200), Err(invalid("age", "must be at most 150")));
+        assert_eq!(
+            person.take_path::<String>("name"),
+            Err(invalid("name", "must have a length of at least 1"))
+        );
+        assert_eq!(person.age, 30);
+        assert_eq!(person.name, "Taro");
+        person.set_path("age", 31).unwrap();
+
+        let mut raw = Reading::Raw(vec![1, 2]);
+        assert_eq!(
+            raw.take_path::<Vec<u8>>("Raw.0"),
+            Err(invalid("Raw.0", "must have a length of at least 2"))
+        );
+        raw.set_path("Raw.0[1]", 3u8).unwrap();
+        assert_eq!(raw.get_path::<u8>("Raw.0[1]"), Ok(&3));
+    }
+}
+
+#[cfg(test)]
+mod tests_invariant {
+    use field_accessor::{DynFieldAccess, FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    #[field_accessor(invariant = "Self::check")]
+    pub struct Person {
+        name: String,
+        #[field_accessor(range(min = 0))]
+        age: i32,
+        life_expectancy: i32,
+    }
+
+    impl Person {
+        fn check(&self) -> Result<(), String> {
+            if self.age > self.life_expectancy {
+                return Err(format!(
+                    "age {} exceeds life expectancy {}",
+                    self.age, self.life_expectancy
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    fn violated(age: i32, life_expectancy: i32) -> FieldAccessError {
+        FieldAccessError::InvariantViolated {
+            reason: format!("age {} exceeds life expectancy {}", age, life_expectancy),
+        }
+    }
+
+    #[test]
+    fn test_rolls_back() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            life_expectancy: 80,
+        };
+        person.set("age", 80).unwrap();
+        assert_eq!(person.set("age", 81), Err(violated(81, 80)));
+        assert_eq!(person.replace("life_expectancy", 79), Err(violated(80, 79)));
+        assert_eq!(
+            person.take("life_expectancy"),
+            Err::<i32, _>(violated(80, 0))
+        );
+        assert_eq!(person.age, 80);
+        assert_eq!(person.life_expectancy, 80);
+
+        person.set("age", 30).unwrap();
+        assert_eq!(person.swap("age", "life_expectancy"), Err(violated(80, 30)));
+        assert_eq!(person.setenum(PersonFieldEnum::age(90)), Err(violated(90, 80)));
+        assert_eq!(
+            person,
+            Person {
+                name: "Taro".to_string(),
+                age: 30,
+                life_expectancy: 80,
+            }
+        );
+
+        assert_eq!(person.replace("life_expectancy", 90), Ok(80));
+        assert_eq!(person.take("age"), Ok::<i32, _>(30));
+        person.set("name", "Jiro".to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_other_writes() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            life_expectancy: 80,
+        };
+        assert_eq!(person.set_from_str("age", "81"), Err(violated(81, 80)));
+        assert_eq!(person.set_dyn("age", Box::new(81)), Err(violated(81, 80)));
+        // Field validators still run first.
+        assert!(matches!(
+            person.set("age", -1),
+            Err(FieldAccessError::ValidationFailed { .. })
+        ));
+        assert_eq!(
+            person,
+            Person {
+                name: "Taro".to_string(),
+                age: 30,
+                life_expectancy: 80,
+            }
+        );
+
+        let fields = vec![
+            PersonFieldEnum::name("Taro".to_string()),
+            PersonFieldEnum::age(90),
+            PersonFieldEnum::life_expectancy(80),
+        ];
+        assert_eq!(Person::try_from_fields(fields), Err(violated(90, 80)));
+    }
+
+    #[test]
+    fn test_paths() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            life_expectancy: 80,
+        };
+        assert_eq!(person.set_path("age", 81), Err(violated(81, 80)));
+        assert_eq!(
+            person.take_path::<i32>("life_expectancy"),
+            Err(violated(30, 0))
+        );
+        assert_eq!(person.age, 30);
+        assert_eq!(person.life_expectancy, 80);
+        person.set_path("age", 80).unwrap();
+    }
+
+    #[test]
+    fn test_validate_all() {
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            life_expectancy: 80,
+        };
+        assert_eq!(person.validate_all(), Ok(()));
+        // Writes through `get_mut` are not checked until asked.
+        *person.get_mut("age").unwrap() = 100;
+        assert_eq!(person.validate_all(), Err(violated(100, 80)));
+        *person.get_mut("age").unwrap() = -1;
+        assert_eq!(
+            person.validate_all(),
+            Err(FieldAccessError::ValidationFailed {
+                field: "age".to_string(),
+                reason: "must be at least 0".to_string(),
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        use field_accessor::PatchOperation;
+        use serde_json::json;
+
+        let mut person = Person {
+            name: "Taro".to_string(),
+            age: 30,
+            life_expectancy: 80,
+        };
+        assert_eq!(person.set_json("age", json!(81)), Err(violated(81, 80)));
+        let patch = vec![
+            PatchOperation::Replace {
+                path: "/name".to_string(),
+                value: json!("Jiro"),
+            },
+            PatchOperation::Replace {
+                path: "/age".to_string(),
+                value: json!(81),
+            },
+        ];
+        assert_eq!(person.apply_json_patch(&patch), Err(violated(81, 80)));
+        assert_eq!(
+            person,
+            Person {
+                name: "Taro".to_string(),
+                age: 30,
+                life_expectancy: 80,
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_transaction {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    #[field_accessor(invariant = "Self::check")]
+    pub struct Account {
+        #[field_accessor(min_len = 1)]
+        owner: String,
+        #[field_accessor(range(min = 0))]
+        balance: i64,
+        limit: i64,
+        #[field_accessor(readonly)]
+        id: u32,
+    }
+
+    impl Account {
+        fn check(&self) -> Result<(), &'static str> {
+            match self.balance <= self.limit {
+                true => Ok(()),
+                false => Err("balance exceeds limit"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_commit() {
+        let mut account = Account {
+            owner: "taro".to_string(),
+            balance: 100,
+            limit: 1000,
+            id: 7,
+        };
+        let mut tx = account.begin();
+        tx.set("owner", "jiro".to_string())
+            .set_from_str("limit", "5000")
+            .setenum(AccountFieldEnum::balance(2000));
+        tx.commit().unwrap();
+        assert_eq!(account.owner, "jiro");
+        assert_eq!((account.balance, account.limit), (2000, 5000));
+
+        // Dropping a transaction discards it.
+        account.begin().set("balance", 0i64);
+        assert_eq!(account.balance, 2000);
+    }
+
+    #[derive(FieldAccessor, Debug, PartialEq)]
+    pub struct Team {
+        #[field_accessor(no_parse)]
+        members: Vec<String>,
+    }
+
+    #[test]
+    fn test_paths_are_rejected() {
+        let mut team = Team {
+            members: vec!["taro".to_string()],
+        };
+        let mut tx = team.begin();
+        tx.set("members[0]", "jiro".to_string());
+        assert_eq!(
+            tx.commit(),
+            Err(FieldAccessError::PathNotSupported {
+                path: "members[0]".to_string()
+            })
+        );
+        let mut tx = team.begin();
+        tx.set_from_str("members.len", "0");
+        assert!(matches!(
+            tx.commit(),
+            Err(FieldAccessError::PathNotSupported { .. })
+        ));
+        assert_eq!(team.members, ["taro"]);
+    }
+
+    #[test]
+    fn test_nothing_changes_on_failure() {
+        let mut account = Account {
+            owner: "taro".to_string(),
+            balance: 100,
+            limit: 1000,
+            id: 7,
+        };
+        let mut tx = account.begin();
+        tx.set("owner", "jiro".to_string())
+            .set("balance", 5u32)
+            .set("limit", 10i64);
+        assert!(matches!(
+            tx.commit(),
+            Err(FieldAccessError::TypeMismatch { .. })
+        ));
+
+        let mut tx = account.begin();
+        tx.set("owner", "jiro".to_string()).set_from_str("limit", "lots");
+        assert!(matches!(
+            tx.commit(),
+            Err(FieldAccessError::ParseFailed { .. })
+        ));
+
+        let mut tx = account.begin();
+        tx.set("owner", "jiro".to_string())
+            .set("limit", 10i64)
+            .set("balance", -1i64);
+        assert!(matches!(
+            tx.commit(),
+            Err(FieldAccessError::ValidationFailed { .. })
+        ));
+
+        let mut tx = account.begin();
+        tx.set("owner", "jiro".to_string())
+            .setenum(AccountFieldEnum::id(8));
+        assert_eq!(
+            tx.commit(),
+            Err(FieldAccessError::ReadOnlyField {
+                field: "id".to_string()
+            })
+        );
+
+        let mut tx = account.begin();
+        tx.set("owner", "jiro".to_string()).set("limit", 10i64);
+        assert_eq!(
+            tx.commit(),
+            Err(FieldAccessError::InvariantViolated {
+                reason: "balance exceeds limit".to_string()
+            })
+        );
+        assert_eq!(
+            account,
+            Account {
+                owner: "taro".to_string(),
+                balance: 100,
+                limit: 1000,
+                id: 7,
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_ops {
+    use std::num::NonZeroU32;
+
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor, Debug, PartialEq, Clone)]
+    pub struct Order {
+        #[field_accessor(rename = "customer-name")]
+        customer: String,
+        #[field_accessor(range(min = 1))]
+        quantity: u32,
+        shipped: u32,
+        #[field_accessor(readonly)]
+        id: u64,
+    }
+
+    #[test]
+    fn test_apply_ops() {
+        let mut order = Order {
+            customer: "taro".to_string(),
+            quantity: 3,
+            shipped: 1,
+            id: 1,
+        };
+        let ops = vec![
+            OrderOp::set(OrderFieldEnum::quantity(5)),
+            OrderOp::swap("quantity", "shipped"),
+            OrderOp::take("customer-name"),
+        ];
+        assert_eq!(
+            ops[0],
+            OrderOp::Set {
+                field: "quantity".to_string(),
+                value: OrderFieldEnum::quantity(5)
+            }
+        );
+        order.apply_ops(ops.clone()).unwrap();
+        assert_eq!(order.customer, "");
+        assert_eq!((order.quantity, order.shipped), (1, 5));
+
+        // Replaying the log rebuilds the same state.
+        let mut replayed = Order {
+            customer: "taro".to_string(),
+            quantity: 3,
+            shipped: 1,
+            id: 1,
+        };
+        for op in ops {
+            replayed.apply_op(op).unwrap();
+        }
+        assert_eq!(replayed, order);
+    }
+
+    #[test]
+    fn test_failing_ops() {
+        let mut order = Order {
+            customer: "taro".to_string(),
+            quantity: 3,
+            shipped: 0,
+            id: 1,
+        };
+        assert_eq!(
+            order.apply_op(OrderOp::Set {
+                field: "shipped".to_string(),
+                value: OrderFieldEnum::quantity(2),
+            }),
+            Err(FieldAccessError::FieldMismatch {
+                field: "shipped".to_string(),
+                found: "quantity",
+            })
+        );
+        assert!(matches!(
+            order.apply_op(OrderOp::set(OrderFieldEnum::quantity(0))),
+            Err(FieldAccessError::ValidationFailed { .. })
+        ));
+        assert_eq!(
+            order.apply_op(OrderOp::take("id")),
+            Err(FieldAccessError::ReadOnlyField {
+                field: "id".to_string()
+            })
+        );
+        assert!(matches!(
+            order.apply_op(OrderOp::swap("quantity", "shipped")),
+            Err(FieldAccessError::ValidationFailed { .. })
+        ));
+        assert!(matches!(
+            order.apply_op(OrderOp::take("missing")),
+            Err(FieldAccessError::UnknownField { .. })
+        ));
+
+        let ops = vec![
+            OrderOp::set(OrderFieldEnum::shipped(2)),
+            OrderOp::swap("quantity", "customer-name"),
+            OrderOp::set(OrderFieldEnum::shipped(3)),
+        ];
+        assert!(matches!(
+            order.apply_ops(ops),
+            Err(FieldAccessError::IncompatibleSwap { .. })
+        ));
+        assert_eq!(order.shipped, 2);
+    }
+
+    #[derive(FieldAccessor)]
+    pub struct Shipment {
+        count: NonZeroU32,
+        limit: NonZeroU32,
+        note: String,
+    }
+
+    #[test]
+    fn test_field_without_default() {
+        let mut shipment = Shipment {
+            count: NonZeroU32::new(1).unwrap(),
+            limit: NonZeroU32::new(5).unwrap(),
+            note: "fragile".to_string(),
+        };
+        let ops = vec![
+            ShipmentOp::set(ShipmentFieldEnum::count(NonZeroU32::new(2).unwrap())),
+            ShipmentOp::swap("count", "limit"),
+            ShipmentOp::take("note"),
+        ];
+        shipment.apply_ops(ops).unwrap();
+        assert_eq!((shipment.count.get(), shipment.limit.get()), (5, 2));
+        assert_eq!(shipment.note, "");
+        assert_eq!(
+            shipment.apply_op(ShipmentOp::take("count")),
+            Err(FieldAccessError::NoDefault {
+                field: "count".to_string()
+            })
+        );
+        assert_eq!(shipment.count.get(), 5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        use serde_json::json;
+
+        let ops = vec![
+            OrderOp::set(OrderFieldEnum::customer("jiro".to_string())),
+            OrderOp::swap("quantity", "shipped"),
+            OrderOp::take("shipped"),
+        ];
+        let log = serde_json::to_value(&ops).unwrap();
+        assert_eq!(
+            log,
+            json!([
+                { "op": "set", "field": "customer-name", "value": { "customer-name": "jiro" } },
+                { "op": "swap", "a": "quantity", "b": "shipped" },
+                { "op": "take", "field": "shipped" },
+            ])
+        );
+        let ops: Vec<OrderOp> = serde_json::from_value(log).unwrap();
+        let mut order = Order {
+            customer: "taro".to_string(),
+            quantity: 3,
+            shipped: 1,
+            id: 1,
+        };
+        order.apply_ops(ops).unwrap();
+        assert_eq!(order.customer, "jiro");
+        assert_eq!((order.quantity, order.shipped), (1, 0));
+    }
+}
+
+#[cfg(feature = "regex")]
+#[cfg(test)]
+mod tests_regex_validation {
+    use field_accessor::{FieldAccessError, FieldAccessor};
+
+    #[derive(FieldAccessor)]
+    pub struct Account {
+        #[field_accessor(regex = "^[a-z][a-z0-9_]*$")]
+        login: String,
+    }
+
+    #[test]
+    fn test_regex() {
+        let mut account = Account {
+            login: "taro".to_string(),
+        };
+        account.set("login", "taro_2".to_string()).unwrap();
+        assert_eq!(
+            account.set("login", "Taro".to_string()),
+            Err(FieldAccessError::ValidationFailed {
+                field: "login".to_string(),
+                reason: "must match the pattern `^[a-z][a-z0-9_]*$`".to_string(),
+            })
+        );
+        assert_eq!(account.login, "taro_2");
+    }
+}
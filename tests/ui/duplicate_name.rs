@@ -0,0 +1,10 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+struct Dog {
+    name: String,
+    #[field_accessor(rename = "name")]
+    nickname: String,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+enum Never {}
+
+fn main() {}
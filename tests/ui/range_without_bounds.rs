@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+struct Person {
+    #[field_accessor(range())]
+    age: u32,
+}
+
+fn main() {}
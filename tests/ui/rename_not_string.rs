@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+struct Dog {
+    #[field_accessor(rename = 1)]
+    name: String,
+}
+
+fn main() {}
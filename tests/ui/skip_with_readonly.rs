@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+struct Dog {
+    #[field_accessor(skip, readonly)]
+    name: String,
+}
+
+fn main() {}
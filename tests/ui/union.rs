@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
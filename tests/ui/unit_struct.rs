@@ -0,0 +1,6 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+struct Marker;
+
+fn main() {}
@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+#[field_accessor(track_change)]
+struct Dog {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+struct Dog {
+    #[field_accessor(hidden)]
+    name: String,
+}
+
+fn main() {}
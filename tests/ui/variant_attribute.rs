@@ -0,0 +1,9 @@
+use field_accessor::FieldAccessor;
+
+#[derive(FieldAccessor)]
+enum Event {
+    #[field_accessor(skip)]
+    Click { x: i32 },
+}
+
+fn main() {}